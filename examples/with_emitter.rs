@@ -1,54 +1,62 @@
 use std::env;
 
 use lyceris::minecraft::{
-    config::ConfigBuilder, emitter::Emitter, install::install, launch::launch,
+    config::ConfigBuilder,
+    emitter::{Emitter, Event},
+    install::install,
+    launch::{launch, wait_for_exit},
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Emitter uses `EventEmitter` inside of it
-    // and it uses tokio::Mutex for locking.
-    // That causes emitter methods to be async.
     let emitter = Emitter::default();
 
-    // Single download progress event send when
-    // a file is being downloaded.
-    emitter
-        .on(
-            "single_download_progress",
-            |(path, current, total): (String, u64, u64)| {
-                println!("Downloading {} - {}/{}", path, current, total);
-            },
-        )
-        .await;
+    // Listeners are called with every event as soon as it's emitted,
+    // so they should return quickly.
+    emitter.on(|event: &Event| match event {
+        // Sent for each chunk of a file being downloaded.
+        Event::DownloadProgress {
+            path,
+            downloaded,
+            total,
+        } => {
+            println!("Downloading {} - {}/{}", path.display(), downloaded, total);
+        }
+        // Java, libraries and assets are downloaded in parallel and
+        // this event is sent when each of them is finished.
+        Event::FileDownloaded {
+            completed, total, ..
+        } => {
+            println!("Downloaded {}/{}", completed, total);
+        }
+        // Sent a few times a second with the bytes received across all files.
+        Event::Progress(progress) => {
+            println!(
+                "{}/{} bytes, {} B/s, ETA {:?}",
+                progress.downloaded, progress.total, progress.bytes_per_second, progress.eta
+            );
+        }
+        Event::PhaseStarted(phase) => println!("{:?} started", phase),
+        _ => {}
+    });
 
-    // Multiple download progress event send when
-    // multiple files are being downloaded.
-    // Java, libraries and assets are downloaded in parallel and
-    // this event is triggered for each file.
-    emitter
-        .on(
-            "multiple_download_progress",
-            |(current, total): (u64, u64)| {
-                println!("Downloading {}/{}", current, total);
-            },
-        )
-        .await;
-
-    // Console event send when a line is printed to the console.
-    // It uses a seperated tokio thread to handle this operation.
-    emitter
-        .on("console", |line: String| {
-            println!("Line: {}", line);
-        })
-        .await;
+    // Events can also be received over a channel,
+    // here the lines printed by the game.
+    let mut events = emitter.channel();
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            if let Event::GameLog(line) = event {
+                println!("Line: {}", line);
+            }
+        }
+    });
 
     let current_dir = env::current_dir()?;
     let config = ConfigBuilder::new(
         current_dir.join("game"),
         "1.21.4",
         lyceris::auth::AuthMethod::Offline {
-            username: "Lyceris",
+            username: "Lyceris".into(),
             // If none given, it will be generated.
             uuid: None,
         },
@@ -60,7 +68,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     install(&config, Some(&emitter)).await?;
 
     // This method never downloads any file and just runs the game.
-    launch(&config, Some(&emitter)).await?.wait().await?;
+    let mut child = launch(&config, Some(&emitter)).await?;
+    wait_for_exit(&mut child, Some(&emitter)).await?;
 
     Ok(())
 }
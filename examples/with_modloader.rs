@@ -1,8 +1,7 @@
 use std::env;
 
 use lyceris::minecraft::{
-    config::ConfigBuilder, install::install, launch::launch,
-    loader::fabric::Fabric,
+    config::ConfigBuilder, install::install, launch::launch, loader::fabric::Fabric,
 };
 
 #[tokio::main]
@@ -12,13 +11,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         current_dir.join("game"),
         "1.21.4",
         lyceris::auth::AuthMethod::Offline {
-            username: "Lyceris",
+            username: "Lyceris".into(),
             // If none given, it will be generated.
             uuid: None,
         },
     )
-    // You can use Fabric, Quilt or Forge here.
-    .loader(Fabric("0.16.9"))
+    // You can use Fabric, Quilt, Forge or NeoForge here.
+    .loader(Fabric("0.16.9".into()))
     .build();
 
     // Install method also checks for broken files
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use oauth2::url::Url;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::error::AuthError;
+
+/// Maximum size of the request head read from the browser.
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+static SUCCESS_PAGE: &str =
+    "<html><body><h3>Signed in, you can close this window.</h3></body></html>";
+static FAILURE_PAGE: &str =
+    "<html><body><h3>Sign in failed, you can close this window.</h3></body></html>";
+
+/// Waits for the browser to be redirected to the listener and returns the query parameters.
+///
+/// Requests without a `code` or `error` parameter, e.g. for the favicon, are
+/// answered with `404 Not Found` and the listener keeps waiting.
+pub(crate) async fn receive_redirect(
+    listener: &TcpListener,
+) -> crate::Result<HashMap<String, String>> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let Some(target) = read_target(&mut stream).await? else {
+            respond(&mut stream, "404 Not Found", "").await;
+            continue;
+        };
+        // Anything could connect to the port, a malformed request doesn't end the sign in.
+        let Ok(url) = Url::parse(&format!("http://127.0.0.1{}", target)) else {
+            respond(&mut stream, "400 Bad Request", "").await;
+            continue;
+        };
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+        if params.contains_key("code") {
+            respond(&mut stream, "200 OK", SUCCESS_PAGE).await;
+            return Ok(params);
+        }
+        if params.contains_key("error") {
+            respond(&mut stream, "200 OK", FAILURE_PAGE).await;
+            return Ok(params);
+        }
+        respond(&mut stream, "404 Not Found", "").await;
+    }
+}
+
+/// Reads the request line and returns its target, or `None` when it isn't a GET request.
+async fn read_target(stream: &mut TcpStream) -> crate::Result<Option<String>> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+        if request.len() > MAX_REQUEST_SIZE {
+            return Err(AuthError::InvalidRedirect("the request is too large".to_string()).into());
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (Some("GET"), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Ok(None);
+    };
+
+    Ok(Some(target.to_string()))
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    // The browser may already have closed the connection, which doesn't matter.
+    stream.write_all(response.as_bytes()).await.ok();
+    stream.shutdown().await.ok();
+}
+
+/// Opens the URL in the default browser of the user.
+pub(crate) fn open_browser(url: &str) -> crate::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command.arg(url).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::receive_redirect;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    #[tokio::test]
+    async fn returns_the_query_of_the_redirect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let browser = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for target in ["/favicon.ico", ":99999", "/?code=abc&state=xyz%3D"] {
+                let mut stream = TcpStream::connect(address).await.unwrap();
+                let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, address);
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                bodies.push(response);
+            }
+            bodies
+        });
+
+        let params = receive_redirect(&listener).await.unwrap();
+        assert_eq!(params.get("code").map(String::as_str), Some("abc"));
+        assert_eq!(params.get("state").map(String::as_str), Some("xyz="));
+
+        let responses = browser.await.unwrap();
+        assert!(responses[0].starts_with("HTTP/1.1 404"));
+        assert!(responses[1].starts_with("HTTP/1.1 400"));
+        assert!(responses[2].starts_with("HTTP/1.1 200"));
+    }
+}
@@ -1,17 +1,35 @@
-use oauth2::{AuthUrl, ClientId, CsrfToken, RedirectUrl, Scope, TokenUrl};
-use reqwest::{Client, Method};
+use oauth2::{
+    AuthUrl, ClientId, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, TokenUrl,
+};
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::{
-    collections::HashMap,
-    time::{SystemTime, UNIX_EPOCH},
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::net::TcpListener;
+
+use crate::{
+    error::{AuthError, Error, XstsError},
+    http::client::client,
+    util::base64::decode_base64,
 };
 
-use crate::{error::Error, http::fetch::fetch_with_options, util::base64::decode_base64};
+use super::loopback::{open_browser, receive_redirect};
 
 pub static CLIENT_ID: &str = "00000000402b5328";
 pub static REDIRECT_URI: &str = "https://login.live.com/oauth20_desktop.srf";
+/// How long `LoopbackLogin::finish` waits for the browser by default.
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 pub static AUTH_URL: &str = "https://login.live.com/oauth20_authorize.srf";
 pub static TOKEN_URL: &str = "https://login.live.com/oauth20_token.srf";
+pub static DEVICE_CODE_URL: &str = "https://login.live.com/oauth20_connect.srf";
+/// Endpoints of Azure applications, used by every client ID other than `CLIENT_ID`.
+pub static AZURE_AUTH_URL: &str =
+    "https://login.microsoftonline.com/consumers/oauth2/v2.0/authorize";
+pub static AZURE_TOKEN_URL: &str = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
+
+static SCOPE: &str = "service::user.auth.xboxlive.com::MBI_SSL";
+static AZURE_SCOPE: &str = "XboxLive.signin offline_access";
+static DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct MSToken {
@@ -19,6 +37,44 @@ struct MSToken {
     refresh_token: String,
 }
 
+/// A pending device code sign in, created by `request_device_code`.
+///
+/// Show `user_code` and `verification_uri` to the user, who signs in
+/// on any device, then pass it to `authenticate_with_device_code`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceCode {
+    pub user_code: String,
+    pub device_code: String,
+    pub verification_uri: String,
+    /// Seconds until the code expires.
+    pub expires_in: u64,
+    /// Seconds to wait between two polls of the token endpoint.
+    pub interval: u64,
+}
+
+/// Response of the token endpoint while polling with a device code.
+#[derive(Deserialize, Debug)]
+struct DeviceTokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Error response of the Microsoft token endpoints.
+#[derive(Deserialize, Debug)]
+struct OAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Error response of the XSTS endpoint.
+#[derive(Deserialize, Debug)]
+struct XstsErrorResponse {
+    #[serde(rename = "XErr")]
+    xerr: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 struct XboxToken {
@@ -70,6 +126,37 @@ struct Cape {
     alias: Option<String>,
 }
 
+/// A product the account owns, e.g. `game_minecraft` or `product_game_pass_pc`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entitlement {
+    pub name: String,
+    pub signature: String,
+}
+
+/// The products an account owns, returned by `get_entitlements`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Entitlements {
+    pub items: Vec<Entitlement>,
+}
+
+impl Entitlements {
+    pub fn owns(&self, name: &str) -> bool {
+        self.items.iter().any(|item| item.name == name)
+    }
+
+    /// Returns whether the account can play Java Edition, bought or through Game Pass.
+    pub fn owns_java_edition(&self) -> bool {
+        [
+            "game_minecraft",
+            "product_minecraft",
+            "product_game_pass_pc",
+            "product_game_pass_ultimate",
+        ]
+        .iter()
+        .any(|name| self.owns(name))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserProfile {
     pub id: Option<String>,
@@ -88,7 +175,7 @@ pub struct MCJWTDecoded {
     exp: u64,
 }
 
-#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+#[derive(Deserialize, Serialize, Default, Clone)]
 pub struct MinecraftAccount {
     pub xuid: String,
     pub exp: u64,
@@ -99,6 +186,95 @@ pub struct MinecraftAccount {
     pub client_id: String,
 }
 
+/// Leaves the tokens out, so logging an account or an error holding one doesn't leak them.
+impl std::fmt::Debug for MinecraftAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MinecraftAccount")
+            .field("xuid", &self.xuid)
+            .field("exp", &self.exp)
+            .field("uuid", &self.uuid)
+            .field("username", &self.username)
+            .field("access_token", &"<redacted>")
+            .field("refresh_token", &"<redacted>")
+            .field("client_id", &self.client_id)
+            .finish()
+    }
+}
+
+/// A pending sign in that redirects the browser to a listener on `127.0.0.1`,
+/// created by `start_loopback_login`.
+///
+/// The authorize URL carries a PKCE challenge and a random state, the state is
+/// checked when the browser comes back and the PKCE verifier is sent along with the code.
+pub struct LoopbackLogin {
+    client_id: String,
+    authorize_url: String,
+    redirect_uri: String,
+    state: CsrfToken,
+    pkce_verifier: PkceCodeVerifier,
+    listener: TcpListener,
+    timeout: Duration,
+}
+
+impl LoopbackLogin {
+    /// Returns the URL to open in the browser of the user.
+    pub fn authorize_url(&self) -> &str {
+        &self.authorize_url
+    }
+
+    /// Sets how long `finish` waits for the browser, five minutes by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Waits for the browser to be redirected back and signs into Minecraft.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuthError::Declined` when the user declined the sign in,
+    /// `AuthError::StateMismatch` when the redirect doesn't belong to this sign in
+    /// and `AuthError::RedirectTimeout` when the browser didn't come back in time,
+    /// e.g. because it was closed.
+    pub async fn finish(self) -> crate::Result<MinecraftAccount> {
+        let mut params = tokio::time::timeout(self.timeout, receive_redirect(&self.listener))
+            .await
+            .map_err(|_| AuthError::RedirectTimeout)??;
+
+        if params.get("state").map(String::as_str) != Some(self.state.secret().as_str()) {
+            return Err(AuthError::StateMismatch.into());
+        }
+        let Some(code) = params.remove("code") else {
+            if params.get("error").map(String::as_str) == Some("access_denied") {
+                return Err(AuthError::Declined.into());
+            }
+            return Err(AuthError::MicrosoftToken(
+                params
+                    .remove("error_description")
+                    .or_else(|| params.remove("error"))
+                    .unwrap_or_else(|| "The sign in failed.".to_string()),
+            )
+            .into());
+        };
+
+        let token_response = client()?
+            .post(AZURE_TOKEN_URL)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("scope", AZURE_SCOPE),
+                ("code", &code),
+                ("grant_type", "authorization_code"),
+                ("redirect_uri", &self.redirect_uri),
+                ("code_verifier", self.pkce_verifier.secret()),
+            ])
+            .send()
+            .await?;
+
+        let ms_token = read_ms_token(token_response, false).await?;
+        authenticate_with_ms_token(ms_token, &self.client_id).await
+    }
+}
+
 pub fn create_link() -> crate::Result<String> {
     let auth_url = AuthUrl::new(AUTH_URL.to_string())?;
     let token_url = TokenUrl::new(TOKEN_URL.to_string())?;
@@ -113,9 +289,7 @@ pub fn create_link() -> crate::Result<String> {
 
     let (authorize_url, _) = client
         .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new(
-            "service::user.auth.xboxlive.com::MBI_SSL".to_string(),
-        ))
+        .add_scope(Scope::new(SCOPE.to_string()))
         .url();
 
     Ok(authorize_url.to_string())
@@ -123,72 +297,284 @@ pub fn create_link() -> crate::Result<String> {
 
 pub async fn authenticate(code: String) -> crate::Result<MinecraftAccount> {
     let ms_token = get_ms_token(&code).await?;
-    let xbox_token = get_xbox_token(&ms_token.access_token).await?;
-    let xsts_token = get_xsts_token(&xbox_token.token).await?;
-    let userhash = xsts_token
-        .display_claims
-        .xui
-        .first()
-        .ok_or(Error::Authentication("No XUI claims found.".to_string()))?
-        .uhs
-        .clone();
+    authenticate_with_ms_token(ms_token, CLIENT_ID).await
+}
+
+/// Starts a sign in with the Azure application of the given client ID that
+/// redirects to a listener on a random port of `127.0.0.1`.
+///
+/// The application has to allow the `http://127.0.0.1` redirect URI of the
+/// "Mobile and desktop applications" platform and personal Microsoft accounts.
+/// Microsoft ignores the port of loopback redirect URIs, so the random port
+/// doesn't have to be registered. `http://localhost` is a different URI and
+/// doesn't match.
+pub async fn start_loopback_login(client_id: &str) -> crate::Result<LoopbackLogin> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let redirect_uri = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+
+    let client = oauth2::basic::BasicClient::new(
+        ClientId::new(client_id.to_string()),
+        None,
+        AuthUrl::new(AZURE_AUTH_URL.to_string())?,
+        Some(TokenUrl::new(AZURE_TOKEN_URL.to_string())?),
+    )
+    .set_redirect_uri(RedirectUrl::new(redirect_uri.clone())?);
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (authorize_url, state) = client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(
+            AZURE_SCOPE
+                .split(' ')
+                .map(|scope| Scope::new(scope.to_string())),
+        )
+        .add_extra_param("prompt", "select_account")
+        .set_pkce_challenge(pkce_challenge)
+        .url();
 
-    obtain_minecraft_account(&xsts_token.token, &userhash, ms_token.refresh_token).await
+    Ok(LoopbackLogin {
+        client_id: client_id.to_string(),
+        authorize_url: authorize_url.to_string(),
+        redirect_uri,
+        state,
+        pkce_verifier,
+        listener,
+        timeout: LOOPBACK_TIMEOUT,
+    })
 }
 
-pub async fn refresh(refresh_token: String) -> crate::Result<MinecraftAccount> {
-    let token_response = Client::new()
-        .post(TOKEN_URL)
+/// Signs in through `start_loopback_login`, opening the authorize URL in the default browser.
+pub async fn authenticate_with_loopback(client_id: &str) -> crate::Result<MinecraftAccount> {
+    let login = start_loopback_login(client_id).await?;
+    open_browser(login.authorize_url())?;
+    login.finish().await
+}
+
+/// Starts a sign in with the OAuth device authorization grant.
+///
+/// Unlike `create_link` it doesn't need a browser on the machine running the
+/// launcher, which makes it usable on headless servers and in terminals.
+pub async fn request_device_code() -> crate::Result<DeviceCode> {
+    let response = client()?
+        .post(DEVICE_CODE_URL)
         .form(&[
             ("client_id", CLIENT_ID),
-            ("scope", "service::user.auth.xboxlive.com::MBI_SSL"),
+            ("scope", SCOPE),
+            ("response_type", "device_code"),
+        ])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AuthError::MicrosoftToken(format!(
+            "Could not request a device code: {}",
+            response.status()
+        ))
+        .into());
+    }
+
+    Ok(response.json().await?)
+}
+
+/// Polls the token endpoint until the user signed in with the device code,
+/// then signs into Minecraft with the same chain as `authenticate`.
+///
+/// # Errors
+///
+/// Returns `AuthError::Declined` when the user declined the sign in and
+/// `AuthError::DeviceCodeExpired` when nobody signed in before the code expired.
+pub async fn authenticate_with_device_code(
+    device_code: &DeviceCode,
+) -> crate::Result<MinecraftAccount> {
+    let deadline = Instant::now() + Duration::from_secs(device_code.expires_in);
+    let mut interval = Duration::from_secs(device_code.interval.max(1));
+
+    let ms_token = loop {
+        // Don't wait for a poll that would only happen after the code expired.
+        if Instant::now() + interval >= deadline {
+            return Err(AuthError::DeviceCodeExpired.into());
+        }
+        tokio::time::sleep(interval).await;
+
+        let response = client()?
+            .post(TOKEN_URL)
+            .form(&[
+                ("client_id", CLIENT_ID),
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("device_code", &device_code.device_code),
+            ])
+            .send()
+            .await?;
+
+        // Pending polls are answered with `400 Bad Request` and an OAuth error,
+        // anything that isn't JSON is a failure of the endpoint itself.
+        let status = response.status();
+        let Ok(response) = response.json::<DeviceTokenResponse>().await else {
+            return Err(AuthError::MicrosoftToken(status.to_string()).into());
+        };
+
+        if status.is_success() {
+            if let (Some(access_token), Some(refresh_token)) =
+                (response.access_token, response.refresh_token)
+            {
+                break MSToken {
+                    access_token,
+                    refresh_token,
+                };
+            }
+        }
+
+        match response.error.as_deref() {
+            Some("authorization_pending") => continue,
+            // The server asks to poll less often.
+            Some("slow_down") => interval += Duration::from_secs(5),
+            Some("expired_token") => return Err(AuthError::DeviceCodeExpired.into()),
+            Some("authorization_declined") => return Err(AuthError::Declined.into()),
+            error => {
+                return Err(AuthError::MicrosoftToken(
+                    response
+                        .error_description
+                        .or_else(|| error.map(str::to_string))
+                        .unwrap_or_else(|| "Unknown device code error.".to_string()),
+                )
+                .into())
+            }
+        }
+    };
+
+    authenticate_with_ms_token(ms_token, CLIENT_ID).await
+}
+
+pub async fn refresh(refresh_token: String) -> crate::Result<MinecraftAccount> {
+    refresh_with_client_id(refresh_token, CLIENT_ID).await
+}
+
+/// Refreshes an account signed in with the given client ID,
+/// which is stored in `MinecraftAccount::client_id`.
+///
+/// # Errors
+///
+/// Returns `AuthError::RefreshTokenExpired` when the user has to sign in again.
+pub async fn refresh_with_client_id(
+    refresh_token: String,
+    client_id: &str,
+) -> crate::Result<MinecraftAccount> {
+    let request = if is_azure(client_id) {
+        client()?.post(AZURE_TOKEN_URL).form(&[
+            ("client_id", client_id),
+            ("scope", AZURE_SCOPE),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+        ])
+    } else {
+        client()?.post(TOKEN_URL).form(&[
+            ("client_id", client_id),
+            ("scope", SCOPE),
             ("grant_type", "refresh_token"),
             ("redirect_uri", REDIRECT_URI),
             ("refresh_token", &refresh_token),
         ])
-        .send()
-        .await?;
+    };
+
+    let ms_token = read_ms_token(request.send().await?, true).await?;
+    authenticate_with_ms_token(ms_token, client_id).await
+}
+
+/// Returns whether the client ID belongs to an Azure application rather than the Live one.
+fn is_azure(client_id: &str) -> bool {
+    !client_id.is_empty() && client_id != CLIENT_ID
+}
 
-    let ms_token: MSToken = token_response.json().await?;
-    let xbox_token = get_xbox_token(&ms_token.access_token).await?;
+/// Exchanges a Microsoft token for an Xbox Live, XSTS and finally a Minecraft token.
+async fn authenticate_with_ms_token(
+    ms_token: MSToken,
+    client_id: &str,
+) -> crate::Result<MinecraftAccount> {
+    // Tokens of Azure applications have to be marked as such for Xbox Live.
+    let rps_ticket = if is_azure(client_id) {
+        format!("d={}", ms_token.access_token)
+    } else {
+        ms_token.access_token
+    };
+    let xbox_token = get_xbox_token(&rps_ticket).await?;
     let xsts_token = get_xsts_token(&xbox_token.token).await?;
     let userhash = xsts_token
         .display_claims
         .xui
         .first()
-        .ok_or(Error::Authentication("No XUI claims found.".to_string()))?
+        .ok_or(AuthError::MissingClaims)?
         .uhs
         .clone();
 
-    obtain_minecraft_account(&xsts_token.token, &userhash, ms_token.refresh_token).await
+    obtain_minecraft_account(
+        &xsts_token.token,
+        &userhash,
+        ms_token.refresh_token,
+        client_id,
+    )
+    .await
 }
 
 async fn obtain_minecraft_account(
     xsts_token: &str,
     userhash: &str,
     refresh_token: String,
+    client_id: &str,
 ) -> crate::Result<MinecraftAccount> {
     let token = get_minecraft_token(xsts_token, userhash).await?;
     let profile = get_profile(token.access_token.clone()).await?;
     let jwt = parse_login_token(&token.access_token)?;
 
-    Ok(MinecraftAccount {
+    let mut account = MinecraftAccount {
         xuid: jwt.xuid,
         exp: jwt.exp,
-        uuid: profile.id.unwrap_or_else(|| "".to_string()),
-        username: profile.name.unwrap_or_else(|| "".to_string()),
+        uuid: String::new(),
+        username: String::new(),
         access_token: token.access_token,
         refresh_token,
-        client_id: CLIENT_ID.to_string(),
-    })
+        client_id: client_id.to_string(),
+    };
+
+    // Entitlements of Game Pass accounts can be empty, a profile proves the
+    // ownership on its own, so they're only checked when there's no profile.
+    let Some(profile) = profile else {
+        return Err(if get_entitlements(&account.access_token)
+            .await?
+            .owns_java_edition()
+        {
+            AuthError::NoProfile(Box::new(account))
+        } else {
+            AuthError::NotOwned
+        }
+        .into());
+    };
+
+    account.uuid = profile.id.unwrap_or_default();
+    account.username = profile.name.unwrap_or_default();
+    Ok(account)
+}
+
+/// Returns the products the account of the Minecraft access token owns.
+pub async fn get_entitlements(access_token: &str) -> crate::Result<Entitlements> {
+    let response = client()?
+        .get("https://api.minecraftservices.com/entitlements/mcstore")
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AuthError::Entitlements(response.status().to_string()).into());
+    }
+
+    Ok(response.json().await?)
 }
 
 async fn get_ms_token(code: &str) -> crate::Result<MSToken> {
-    let token_response = Client::new()
+    let token_response = client()?
         .post(TOKEN_URL)
         .form(&[
             ("client_id", CLIENT_ID),
-            ("scope", "service::user.auth.xboxlive.com::MBI_SSL"),
+            ("scope", SCOPE),
             ("code", code),
             ("grant_type", "authorization_code"),
             ("redirect_uri", REDIRECT_URI),
@@ -196,8 +582,27 @@ async fn get_ms_token(code: &str) -> crate::Result<MSToken> {
         .send()
         .await?;
 
-    let ms_token: MSToken = token_response.json().await?;
-    Ok(ms_token)
+    read_ms_token(token_response, false).await
+}
+
+/// Reads the token from a response of a Microsoft token endpoint.
+///
+/// A rejected grant of a refresh is reported as `AuthError::RefreshTokenExpired`.
+async fn read_ms_token(response: Response, refreshing: bool) -> crate::Result<MSToken> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json().await?);
+    }
+
+    let error = response.json::<OAuthError>().await.ok();
+    Err(match error {
+        Some(error) if refreshing && error.error == "invalid_grant" => {
+            AuthError::RefreshTokenExpired
+        }
+        Some(error) => AuthError::MicrosoftToken(error.error_description.unwrap_or(error.error)),
+        None => AuthError::MicrosoftToken(status.to_string()),
+    }
+    .into())
 }
 
 async fn get_xbox_token(ms_token: &str) -> crate::Result<XboxToken> {
@@ -211,7 +616,12 @@ async fn get_xbox_token(ms_token: &str) -> crate::Result<XboxToken> {
         "TokenType": "JWT"
     });
 
-    fetch_token("https://user.auth.xboxlive.com/user/authenticate", body).await
+    let response = post_json("https://user.auth.xboxlive.com/user/authenticate", &body).await?;
+    if !response.status().is_success() {
+        return Err(AuthError::XboxLive(response.status().to_string()).into());
+    }
+
+    Ok(response.json().await?)
 }
 
 async fn get_xsts_token(xbox_token: &str) -> crate::Result<XstsToken> {
@@ -224,25 +634,26 @@ async fn get_xsts_token(xbox_token: &str) -> crate::Result<XstsToken> {
         "TokenType": "JWT"
     });
 
-    fetch_token("https://xsts.auth.xboxlive.com/xsts/authorize", body).await
-}
-
-async fn fetch_token<T: for<'de> Deserialize<'de>>(
-    url: &str,
-    body: serde_json::Value,
-) -> crate::Result<T> {
-    let token_response: T = fetch_with_options(
-        url,
-        Some(crate::http::fetch::FetchOptions {
-            method: Method::POST,
-            headers: HashMap::default(),
-            query_params: HashMap::default(),
-            body: Some(body),
-        }),
-    )
-    .await?;
+    let response = post_json("https://xsts.auth.xboxlive.com/xsts/authorize", &body).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        return Err(match response.json::<XstsErrorResponse>().await {
+            Ok(error) => AuthError::Xsts(XstsError::from_code(error.xerr)),
+            Err(_) => AuthError::XboxLive(status.to_string()),
+        }
+        .into());
+    }
+
+    Ok(response.json().await?)
+}
 
-    Ok(token_response)
+async fn post_json<T: Serialize>(url: &str, body: &T) -> crate::Result<Response> {
+    Ok(client()?
+        .post(url)
+        .header("Accept", "application/json")
+        .json(body)
+        .send()
+        .await?)
 }
 
 async fn get_minecraft_token(xsts_token: &str, userhash: &str) -> crate::Result<MinecraftResponse> {
@@ -250,16 +661,30 @@ async fn get_minecraft_token(xsts_token: &str, userhash: &str) -> crate::Result<
         "identityToken": format!("XBL3.0 x={};{}", userhash, xsts_token)
     });
 
-    fetch_with_options(
+    let response = post_json(
         "https://api.minecraftservices.com/authentication/login_with_xbox",
-        Some(crate::http::fetch::FetchOptions {
-            method: Method::POST,
-            headers: HashMap::default(),
-            query_params: HashMap::default(),
-            body: Some(body),
-        }),
+        &body,
     )
-    .await
+    .await?;
+
+    match response.status() {
+        status if status.is_success() => Ok(response.json().await?),
+        StatusCode::TOO_MANY_REQUESTS => Err(AuthError::Throttled {
+            retry_after: retry_after(&response),
+        }
+        .into()),
+        status => Err(AuthError::MinecraftLogin(status.to_string()).into()),
+    }
+}
+
+/// Reads the `Retry-After` header of a throttled response in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
 }
 
 fn parse_login_token(mc_token: &str) -> crate::Result<MCJWTDecoded> {
@@ -276,27 +701,37 @@ fn parse_login_token(mc_token: &str) -> crate::Result<MCJWTDecoded> {
     Ok(decoded)
 }
 
-async fn get_profile(access_token: String) -> crate::Result<UserProfile> {
+/// Returns the profile of the account, or `None` when it didn't create one yet.
+async fn get_profile(access_token: String) -> crate::Result<Option<UserProfile>> {
     let api_url = "https://api.minecraftservices.com/minecraft/profile";
-    let client = Client::new();
-
-    let response = client
+    let response = client()?
         .get(api_url)
         .header("Authorization", format!("Bearer {}", access_token))
         .send()
         .await?;
 
+    // Accounts without a profile are answered with `404 Not Found` and a `NOT_FOUND` error.
+    match response.status() {
+        status if status.is_success() || status == StatusCode::NOT_FOUND => {}
+        StatusCode::UNAUTHORIZED => return Err(AuthError::Unauthorized.into()),
+        StatusCode::TOO_MANY_REQUESTS => {
+            return Err(AuthError::Throttled {
+                retry_after: retry_after(&response),
+            }
+            .into())
+        }
+        status => return Err(AuthError::Profile(status.to_string()).into()),
+    }
+
     let profile = response.json::<UserProfile>().await?;
 
     if let Some(error) = profile.error {
         match error.as_str() {
-            "NOT_FOUND" => Err(Error::Authentication(
-                "Account does not own Minecraft.".to_string(),
-            )),
-            _ => Err(Error::Authentication(error)),
+            "NOT_FOUND" => Ok(None),
+            _ => Err(AuthError::Profile(profile.error_message.unwrap_or(error)).into()),
         }
     } else {
-        Ok(profile)
+        Ok(Some(profile))
     }
 }
 
@@ -307,3 +742,58 @@ pub fn validate(exp: u64) -> bool {
         .unwrap()
         .as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Entitlements, MinecraftAccount};
+    use crate::error::{AuthError, Error};
+
+    #[test]
+    fn hides_the_tokens_of_accounts_without_a_profile() {
+        let account = MinecraftAccount {
+            username: "player".to_string(),
+            access_token: "secret-access".to_string(),
+            refresh_token: "secret-refresh".to_string(),
+            ..Default::default()
+        };
+        let error = Error::from(AuthError::NoProfile(Box::new(account)));
+
+        let debug = format!("{:?}", error);
+        assert!(debug.contains("player"));
+        assert!(!debug.contains("secret"));
+    }
+
+    #[test]
+    fn reads_owned_products_from_entitlements() {
+        let purchased: Entitlements = serde_json::from_str(
+            r#"{
+                "items": [
+                    { "name": "product_minecraft", "signature": "eyJ0..." },
+                    { "name": "game_minecraft", "signature": "eyJ1..." }
+                ],
+                "signature": "eyJ2...",
+                "keyId": "1"
+            }"#,
+        )
+        .unwrap();
+        assert!(purchased.owns("product_minecraft"));
+        assert!(purchased.owns("game_minecraft"));
+        assert!(purchased.owns_java_edition());
+
+        let game_pass: Entitlements = serde_json::from_str(
+            r#"{
+                "items": [{ "name": "product_game_pass_pc", "signature": "eyJ0..." }],
+                "signature": "eyJ1...",
+                "keyId": "1"
+            }"#,
+        )
+        .unwrap();
+        assert!(!game_pass.owns("game_minecraft"));
+        assert!(game_pass.owns_java_edition());
+
+        let empty: Entitlements =
+            serde_json::from_str(r#"{ "items": [], "signature": "eyJ0...", "keyId": "1" }"#)
+                .unwrap();
+        assert!(!empty.owns_java_edition());
+    }
+}
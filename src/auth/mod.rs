@@ -1,18 +1,20 @@
 use serde::{Deserialize, Serialize};
 
+mod loopback;
 pub mod microsoft;
+pub mod store;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum AuthMethod {
     Microsoft {
         access_token: String,
         refresh_token: String,
         uuid: String,
         xuid: String,
-        username: String
+        username: String,
     },
     Offline {
-        username: &'static str,
-        uuid: Option<String>
-    }
-}
\ No newline at end of file
+        username: String,
+        uuid: Option<String>,
+    },
+}
@@ -0,0 +1,389 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::error::Error;
+
+use super::{
+    microsoft::{refresh_with_client_id, MinecraftAccount, CLIENT_ID},
+    AuthMethod,
+};
+
+const ACCOUNTS_FILE: &str = "accounts.dat";
+const KEY_FILE: &str = "accounts.key";
+const NONCE_SIZE: usize = 12;
+
+/// Accounts expiring within this time are refreshed by `AccountManager::auth_method`.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60 * 60);
+
+/// The accounts of an `AccountManager` as they're persisted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AccountStore {
+    pub accounts: Vec<MinecraftAccount>,
+    /// UUID of the selected account.
+    pub selected: Option<String>,
+}
+
+/// Where an `AccountManager` persists its accounts.
+///
+/// The trait is object safe so launchers can store the accounts somewhere
+/// else, e.g. in the keychain of the operating system.
+pub trait AccountStorage: Send + Sync {
+    /// Returns the stored accounts, or an empty store when nothing was saved yet.
+    fn load(&self) -> BoxFuture<'_, crate::Result<AccountStore>>;
+    fn save<'a>(&'a self, store: &'a AccountStore) -> BoxFuture<'a, crate::Result<()>>;
+}
+
+/// Stores the accounts in a file encrypted with AES-256-GCM.
+///
+/// The file starts with the random nonce of the last write followed by
+/// the encrypted JSON of the store.
+pub struct EncryptedFileStorage {
+    path: PathBuf,
+    key: Key<Aes256Gcm>,
+}
+
+impl EncryptedFileStorage {
+    pub fn new<P: AsRef<Path>>(path: P, key: [u8; 32]) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            key: key.into(),
+        }
+    }
+
+    /// Uses the key stored in `key_path`, generating a random one when neither
+    /// the key nor the accounts exist yet.
+    ///
+    /// The key file is only readable by the current user on Unix. Keeping it next
+    /// to the accounts protects them from being shared by accident, not from
+    /// other programs running as the same user.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MissingAccountKey` when the accounts exist without their key,
+    /// instead of replacing the key and losing them.
+    pub async fn with_key_file<P: AsRef<Path>, K: AsRef<Path>>(
+        path: P,
+        key_path: K,
+    ) -> crate::Result<Self> {
+        let key_path = key_path.as_ref();
+        let path = path.as_ref();
+        let key = if key_path.is_file() {
+            let key = fs::read(key_path).await?;
+            <[u8; 32]>::try_from(key.as_slice())
+                .map_err(|_| Error::Parse(format!("Account key {}", key_path.display())))?
+        } else if path.is_file() {
+            return Err(Error::MissingAccountKey {
+                key: key_path.to_path_buf(),
+                store: path.to_path_buf(),
+            });
+        } else {
+            let key: [u8; 32] = Aes256Gcm::generate_key(OsRng).into();
+            write_private(key_path, &key).await?;
+            key
+        };
+
+        Ok(Self::new(path, key))
+    }
+}
+
+impl AccountStorage for EncryptedFileStorage {
+    fn load(&self) -> BoxFuture<'_, crate::Result<AccountStore>> {
+        Box::pin(async move {
+            if !self.path.is_file() {
+                return Ok(AccountStore::default());
+            }
+
+            let data = fs::read(&self.path).await?;
+            if data.len() < NONCE_SIZE {
+                return Err(Error::Parse(format!(
+                    "Account store {}",
+                    self.path.display()
+                )));
+            }
+            let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+            let plaintext = Aes256Gcm::new(&self.key)
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| {
+                    Error::Fail(format!(
+                        "Could not decrypt the account store {}",
+                        self.path.display()
+                    ))
+                })?;
+
+            Ok(serde_json::from_slice(&plaintext)?)
+        })
+    }
+
+    fn save<'a>(&'a self, store: &'a AccountStore) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move {
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = Aes256Gcm::new(&self.key)
+                .encrypt(&nonce, serde_json::to_vec(store)?.as_slice())
+                .map_err(|_| Error::Fail("Could not encrypt the account store".to_string()))?;
+
+            write_private(&self.path, &[nonce.as_slice(), &ciphertext].concat()).await
+        })
+    }
+}
+
+/// Keeps multiple Microsoft accounts along with the selected one, and
+/// refreshes their tokens before they're used.
+///
+/// Every change is saved to the storage right away. `launch` doesn't know about
+/// the manager, call `auth_method` for the `Config::authentication` of every
+/// launch so the token it starts the game with is fresh.
+pub struct AccountManager {
+    storage: Box<dyn AccountStorage>,
+    store: AccountStore,
+    refresh_margin: Duration,
+}
+
+impl AccountManager {
+    /// Loads the accounts from the given storage.
+    pub async fn new(storage: impl AccountStorage + 'static) -> crate::Result<Self> {
+        let store = storage.load().await?;
+        Ok(Self {
+            storage: Box::new(storage),
+            store,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+        })
+    }
+
+    /// Loads the accounts from `accounts.dat` in the given directory,
+    /// encrypted with the key in `accounts.key`.
+    pub async fn open<P: AsRef<Path>>(dir: P) -> crate::Result<Self> {
+        let dir = dir.as_ref();
+        Self::new(
+            EncryptedFileStorage::with_key_file(dir.join(ACCOUNTS_FILE), dir.join(KEY_FILE))
+                .await?,
+        )
+        .await
+    }
+
+    /// Sets how long before their expiry accounts are refreshed, one hour by default.
+    pub fn refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    pub fn accounts(&self) -> &[MinecraftAccount] {
+        &self.store.accounts
+    }
+
+    pub fn get(&self, uuid: &str) -> Option<&MinecraftAccount> {
+        self.store
+            .accounts
+            .iter()
+            .find(|account| account.uuid == uuid)
+    }
+
+    pub fn selected(&self) -> Option<&MinecraftAccount> {
+        self.get(self.store.selected.as_deref()?)
+    }
+
+    /// Adds an account or replaces the one with the same UUID.
+    /// The account is selected when no account was selected before.
+    pub async fn add(&mut self, account: MinecraftAccount) -> crate::Result<()> {
+        if self.store.selected.is_none() {
+            self.store.selected = Some(account.uuid.clone());
+        }
+        match self
+            .store
+            .accounts
+            .iter_mut()
+            .find(|existing| existing.uuid == account.uuid)
+        {
+            Some(existing) => *existing = account,
+            None => self.store.accounts.push(account),
+        }
+        self.save().await
+    }
+
+    /// Removes an account, unselecting it when it was selected.
+    pub async fn remove(&mut self, uuid: &str) -> crate::Result<Option<MinecraftAccount>> {
+        let Some(index) = self
+            .store
+            .accounts
+            .iter()
+            .position(|account| account.uuid == uuid)
+        else {
+            return Ok(None);
+        };
+
+        let account = self.store.accounts.remove(index);
+        if self.store.selected.as_deref() == Some(uuid) {
+            self.store.selected = None;
+        }
+        self.save().await?;
+        Ok(Some(account))
+    }
+
+    pub async fn select(&mut self, uuid: &str) -> crate::Result<()> {
+        if self.get(uuid).is_none() {
+            return Err(Error::NotFound(format!("Account '{}'", uuid)));
+        }
+        self.store.selected = Some(uuid.to_string());
+        self.save().await
+    }
+
+    /// Refreshes the account when it expired or expires within the refresh margin.
+    pub async fn refresh_if_needed(&mut self, uuid: &str) -> crate::Result<&MinecraftAccount> {
+        let account = self
+            .get(uuid)
+            .ok_or_else(|| Error::NotFound(format!("Account '{}'", uuid)))?;
+
+        if expires_within(account.exp, self.refresh_margin) {
+            let client_id = if account.client_id.is_empty() {
+                CLIENT_ID
+            } else {
+                &account.client_id
+            };
+            let refreshed =
+                refresh_with_client_id(account.refresh_token.clone(), client_id).await?;
+            self.add(refreshed).await?;
+        }
+
+        self.get(uuid)
+            .ok_or_else(|| Error::NotFound(format!("Account '{}'", uuid)))
+    }
+
+    /// Returns the selected account for `Config::authentication`, refreshing it first when needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NotFound` when no account is selected and
+    /// `AuthError::RefreshTokenExpired` when the account has to sign in again.
+    pub async fn auth_method(&mut self) -> crate::Result<AuthMethod> {
+        let uuid = self
+            .store
+            .selected
+            .clone()
+            .ok_or_else(|| Error::NotFound("Selected account".to_string()))?;
+
+        Ok(self.refresh_if_needed(&uuid).await?.into())
+    }
+
+    async fn save(&self) -> crate::Result<()> {
+        self.storage.save(&self.store).await
+    }
+}
+
+impl From<&MinecraftAccount> for AuthMethod {
+    fn from(account: &MinecraftAccount) -> Self {
+        AuthMethod::Microsoft {
+            access_token: account.access_token.clone(),
+            refresh_token: account.refresh_token.clone(),
+            uuid: account.uuid.clone(),
+            xuid: account.xuid.clone(),
+            username: account.username.clone(),
+        }
+    }
+}
+
+fn expires_within(exp: u64, margin: Duration) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    exp <= now.saturating_add(margin.as_secs())
+}
+
+/// Writes a file only the current user can read, replacing it atomically.
+async fn write_private(path: &Path, contents: &[u8]) -> crate::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.is_dir() {
+            fs::create_dir_all(parent).await?;
+        }
+    }
+
+    let mut temp_name = path.file_name().map(OsString::from).unwrap_or_default();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    // A leftover temp file would keep its permissions, so it's created anew
+    // and is never readable by others, not even before it's renamed.
+    if temp_path.is_file() {
+        fs::remove_file(&temp_path).await?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&temp_path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountManager, AccountStorage, EncryptedFileStorage};
+    use crate::{
+        auth::{microsoft::MinecraftAccount, AuthMethod},
+        error::Error,
+    };
+
+    #[tokio::test]
+    async fn persists_encrypted_accounts_and_selection() {
+        let dir = std::env::temp_dir().join(format!("lyceris-accounts-{}", uuid::Uuid::new_v4()));
+        let account = |uuid: &str| MinecraftAccount {
+            uuid: uuid.to_string(),
+            username: format!("player-{}", uuid),
+            access_token: "token".to_string(),
+            exp: u64::MAX,
+            ..Default::default()
+        };
+
+        let mut manager = AccountManager::open(&dir).await.unwrap();
+        manager.add(account("a")).await.unwrap();
+        manager.add(account("b")).await.unwrap();
+        manager.select("b").await.unwrap();
+        assert!(manager.select("c").await.is_err());
+
+        let data = std::fs::read(dir.join("accounts.dat")).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("player-a"));
+        #[cfg(unix)]
+        for file in ["accounts.dat", "accounts.key"] {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join(file))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file);
+        }
+
+        let mut manager = AccountManager::open(&dir).await.unwrap();
+        assert_eq!(manager.accounts().len(), 2);
+        let Ok(AuthMethod::Microsoft { username, .. }) = manager.auth_method().await else {
+            panic!("expected the selected Microsoft account");
+        };
+        assert_eq!(username, "player-b");
+
+        // A different key can't read the store.
+        let storage = EncryptedFileStorage::new(dir.join("accounts.dat"), [0; 32]);
+        assert!(storage.load().await.is_err());
+
+        // Losing the key doesn't silently replace it.
+        std::fs::remove_file(dir.join("accounts.key")).unwrap();
+        assert!(matches!(
+            AccountManager::open(&dir).await,
+            Err(Error::MissingAccountKey { .. })
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,21 +1,47 @@
+use std::{path::PathBuf, time::Duration};
+
 use thiserror::Error;
 
+use crate::auth::microsoft::MinecraftAccount;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Unknown {0} version")]
     UnknownVersion(String),
     #[error("{0} not found")]
     NotFound(String),
+    #[error("{0} already exists")]
+    AlreadyExists(String),
     #[error("Could not parse: {0}")]
     Parse(String),
     #[error("Could not take optional value: {0}")]
     Take(String),
     #[error("Download failed with status code: {0}")]
     Download(String),
+    #[error("Hash mismatch for {path:?}: expected {expected}, got {actual}")]
+    HashMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[error("Size mismatch for {path:?}: expected {expected} bytes, got {actual}")]
+    SizeMismatch {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("Missing cached files: {}", display_paths(.0))]
+    MissingFiles(Vec<PathBuf>),
+    #[error("Operation cancelled")]
+    Cancelled,
+    /// The key of an existing account store is gone, so the accounts can't be
+    /// decrypted anymore. Removing the store starts over with no accounts.
+    #[error("The key {key:?} of the account store {store:?} is missing")]
+    MissingAccountKey { key: PathBuf, store: PathBuf },
     #[error("Timeout error")]
     Timeout(#[from] tokio::time::error::Elapsed),
-    #[error("{0}")]
-    Authentication(String),
+    #[error(transparent)]
+    Authentication(#[from] AuthError),
     #[error("Malformed token: {0}")]
     MalformedToken(String),
     #[error("Operation failed: {0}")]
@@ -36,4 +62,118 @@ pub enum Error {
     FromUTF8(#[from] std::string::FromUtf8Error),
     #[error(transparent)]
     OAuthUrlParse(#[from] oauth2::url::ParseError),
+    #[error(transparent)]
+    Join(#[from] tokio::task::JoinError),
+}
+
+/// Failures of the steps of the Microsoft, Xbox Live and Minecraft sign in.
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Microsoft sign in failed: {0}")]
+    MicrosoftToken(String),
+    #[error("The refresh token expired or was revoked, sign in again")]
+    RefreshTokenExpired,
+    #[error("The device code expired")]
+    DeviceCodeExpired,
+    #[error("The user declined the sign in")]
+    Declined,
+    #[error("The state of the redirect doesn't match the sign in")]
+    StateMismatch,
+    #[error("Invalid redirect: {0}")]
+    InvalidRedirect(String),
+    #[error("The browser didn't return to the launcher in time")]
+    RedirectTimeout,
+    #[error("Xbox Live authentication failed with status code: {0}")]
+    XboxLive(String),
+    #[error(transparent)]
+    Xsts(#[from] XstsError),
+    #[error("No XUI claims found")]
+    MissingClaims,
+    #[error("Too many requests to the Minecraft services, try again later")]
+    Throttled { retry_after: Option<Duration> },
+    /// The Minecraft access token was rejected, refresh the account.
+    #[error("The Minecraft access token is invalid or expired")]
+    Unauthorized,
+    #[error("Minecraft login failed with status code: {0}")]
+    MinecraftLogin(String),
+    #[error("Account does not own Minecraft")]
+    NotOwned,
+    /// The account owns the game but didn't pick a name yet, which is common
+    /// for Game Pass accounts. Holds the signed in account without a name.
+    #[error("Account owns Minecraft but has no profile yet, create one on minecraft.net")]
+    NoProfile(Box<MinecraftAccount>),
+    #[error("Could not get the entitlements: {0}")]
+    Entitlements(String),
+    #[error("Could not get the Minecraft profile: {0}")]
+    Profile(String),
+}
+
+/// Reasons the XSTS authorization was denied, from the `XErr` code of the response.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XstsError {
+    #[error("The account is banned from Xbox Live")]
+    Banned,
+    #[error("The account is restricted by parental controls")]
+    Restricted,
+    #[error("The account has no Xbox profile, create one on xbox.com")]
+    NoXboxAccount,
+    #[error("The account has to accept the Xbox Live terms of service on xbox.com")]
+    TermsNotAccepted,
+    #[error("Xbox Live is not available in the country of the account")]
+    CountryUnavailable,
+    #[error("The account has to complete the adult verification on xbox.com")]
+    AdultVerificationRequired,
+    #[error("The account belongs to a child and has to be added to a family by an adult")]
+    ChildAccount,
+    #[error("XSTS authorization failed with XErr {0}")]
+    Unknown(u64),
+}
+
+impl XstsError {
+    pub fn from_code(code: u64) -> Self {
+        match code {
+            2148916227 => XstsError::Banned,
+            2148916229 => XstsError::Restricted,
+            2148916233 => XstsError::NoXboxAccount,
+            2148916234 => XstsError::TermsNotAccepted,
+            2148916235 => XstsError::CountryUnavailable,
+            2148916236 | 2148916237 => XstsError::AdultVerificationRequired,
+            2148916238 => XstsError::ChildAccount,
+            code => XstsError::Unknown(code),
+        }
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XstsError;
+
+    #[test]
+    fn maps_xerr_codes() {
+        for (code, expected) in [
+            (2148916233, XstsError::NoXboxAccount),
+            (2148916235, XstsError::CountryUnavailable),
+            (2148916236, XstsError::AdultVerificationRequired),
+            (2148916237, XstsError::AdultVerificationRequired),
+            (2148916238, XstsError::ChildAccount),
+        ] {
+            assert_eq!(XstsError::from_code(code), expected, "XErr {}", code);
+        }
+    }
+
+    #[test]
+    fn keeps_unknown_xerr_codes() {
+        assert_eq!(
+            XstsError::from_code(2148916239),
+            XstsError::Unknown(2148916239)
+        );
+    }
 }
@@ -0,0 +1,354 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use super::{client::client, mirror::Mirrors};
+use crate::{
+    error::Error,
+    util::json::{read_json, write_json},
+};
+
+/// Settings of the on-disk metadata cache.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CachePolicy {
+    /// Whether fetched metadata is cached at all.
+    pub enabled: bool,
+    /// How long a cached document is used without asking the server.
+    /// Older documents are revalidated with a conditional request.
+    pub max_age: Duration,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// Where metadata such as version lists is fetched from: the mirrors and the
+/// on-disk metadata cache.
+///
+/// Listing versions only needs a source, so a version picker works before
+/// any `Config` exists. `Config::metadata_source` returns the one an install uses.
+#[derive(Clone, Debug)]
+pub struct MetadataSource {
+    pub mirrors: Mirrors,
+    /// The directory cached documents are stored in.
+    pub cache_dir: PathBuf,
+    pub cache_policy: CachePolicy,
+}
+
+impl MetadataSource {
+    /// Uses the mirrors and the default cache policy with the given cache directory.
+    pub fn new<P: AsRef<Path>>(mirrors: Mirrors, cache_dir: P) -> Self {
+        Self {
+            mirrors,
+            cache_dir: cache_dir.as_ref().to_path_buf(),
+            cache_policy: CachePolicy::default(),
+        }
+    }
+
+    /// Fetches a JSON document through the mirrors and the metadata cache.
+    pub async fn fetch<T: DeserializeOwned>(&self, url: &str) -> crate::Result<T> {
+        fetch_cached(url, &self.cache_dir, &self.cache_policy, &self.mirrors).await
+    }
+
+    /// Fetches a text document through the mirrors, text isn't cached.
+    pub async fn fetch_text(&self, url: &str) -> crate::Result<String> {
+        self.mirrors.fetch_text(url).await
+    }
+}
+
+/// A cached document along with the validators the server returned for it.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix timestamp in seconds of the last time the server confirmed the document.
+    validated_at: u64,
+    body: String,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self, max_age: Duration) -> bool {
+        now().saturating_sub(self.validated_at) < max_age.as_secs()
+    }
+}
+
+/// Fetches a JSON document through the on-disk metadata cache in `cache_dir`.
+///
+/// A cached document younger than `policy.max_age` is returned without a request.
+/// Otherwise it's revalidated with `If-None-Match` and `If-Modified-Since`, and
+/// a `304 Not Modified` response keeps the cached body. When the server can't
+/// be reached at all, a stale cached document is returned instead of the error.
+///
+/// # Parameters
+///
+/// - `url`: The upstream URL of the document, which is also the cache key.
+/// - `cache_dir`: The directory cached documents are stored in.
+/// - `policy`: Whether the cache is used and how long documents stay fresh.
+/// - `mirrors`: The mirrors the request is sent to, tried in order.
+///
+/// # Errors
+///
+/// Returns the error of the last tried URL when nothing is cached, or
+/// `Error::Serde` when the document can't be deserialized into `T`.
+pub async fn fetch_cached<T: DeserializeOwned>(
+    url: &str,
+    cache_dir: &Path,
+    policy: &CachePolicy,
+    mirrors: &Mirrors,
+) -> crate::Result<T> {
+    if !policy.enabled {
+        return mirrors.fetch(url).await;
+    }
+
+    let entry_path = entry_path(cache_dir, url);
+    let cached: Option<CacheEntry> = if entry_path.is_file() {
+        // A corrupt entry is treated like a missing one.
+        read_json(&entry_path).await.ok()
+    } else {
+        None
+    };
+
+    if let Some(entry) = &cached {
+        if entry.is_fresh(policy.max_age) {
+            return Ok(serde_json::from_str(&entry.body)?);
+        }
+    }
+
+    let entry = match revalidate(url, cached.as_ref(), mirrors).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => {
+            let mut entry = cached.ok_or_else(|| Error::NotFound(url.to_string()))?;
+            entry.validated_at = now();
+            entry
+        }
+        Err(e) => {
+            return match cached {
+                Some(entry) => Ok(serde_json::from_str(&entry.body)?),
+                None => Err(e),
+            }
+        }
+    };
+
+    let value = serde_json::from_str(&entry.body)?;
+    write_json(&entry_path, &entry).await?;
+    Ok(value)
+}
+
+/// Sends a conditional request for the document, returning `None` when the cached one is still valid.
+async fn revalidate(
+    url: &str,
+    cached: Option<&CacheEntry>,
+    mirrors: &Mirrors,
+) -> crate::Result<Option<CacheEntry>> {
+    let mut last_error = None;
+
+    for candidate in mirrors.candidates(url) {
+        let mut request = client()?.get(&candidate);
+        if let Some(entry) = cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                last_error = Some(e.into());
+                continue;
+            }
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            last_error = Some(Error::Download(response.status().to_string()));
+            continue;
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        return Ok(Some(CacheEntry {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            validated_at: now(),
+            body: response.text().await?,
+        }));
+    }
+
+    Err(last_error.unwrap_or_else(|| Error::NotFound(url.to_string())))
+}
+
+fn entry_path(cache_dir: &Path, url: &str) -> PathBuf {
+    let key = format!("{:x}", Sha1::digest(url.as_bytes()));
+    cache_dir.join(format!("{}.json", key))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use super::{entry_path, fetch_cached, now, CacheEntry, CachePolicy};
+    use crate::{
+        http::mirror::Mirrors,
+        util::json::{read_json, write_json},
+    };
+
+    /// Answers each connection with the next of `responses` and sends the
+    /// head of every request it received to the returned channel.
+    async fn serve(responses: Vec<&'static str>) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/manifest.json", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                sender
+                    .send(String::from_utf8_lossy(&request).to_lowercase())
+                    .unwrap();
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.ok();
+            }
+        });
+
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn revalidates_stale_entries_with_conditional_requests() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("lyceris-cache-{}", uuid::Uuid::new_v4()));
+        let (url, mut requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Sat, 01 Feb 2025 00:00:00 GMT\r\n\
+             Content-Length: 3\r\nConnection: close\r\n\r\n[1]",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nETag: \"v2\"\r\nContent-Length: 3\r\nConnection: close\r\n\r\n[2]",
+        ])
+        .await;
+        let policy = CachePolicy {
+            max_age: Duration::ZERO,
+            ..CachePolicy::default()
+        };
+        let path = entry_path(&cache_dir, &url);
+
+        let value: Vec<u32> = fetch_cached(&url, &cache_dir, &policy, &Mirrors::new())
+            .await
+            .unwrap();
+        assert_eq!(value, vec![1]);
+        let request = requests.recv().await.unwrap();
+        assert!(!request.contains("if-none-match"));
+
+        // Not modified: the cached body is kept and marked as validated again.
+        let mut entry: CacheEntry = read_json(&path).await.unwrap();
+        entry.validated_at = 0;
+        write_json(&path, &entry).await.unwrap();
+
+        let value: Vec<u32> = fetch_cached(&url, &cache_dir, &policy, &Mirrors::new())
+            .await
+            .unwrap();
+        assert_eq!(value, vec![1]);
+        let request = requests.recv().await.unwrap();
+        assert!(request.contains("if-none-match: \"v1\""));
+        assert!(request.contains("if-modified-since: sat, 01 feb 2025 00:00:00 gmt"));
+        let entry: CacheEntry = read_json(&path).await.unwrap();
+        assert!(entry.validated_at > 0);
+        assert_eq!(entry.body, "[1]");
+
+        // Modified: the new body replaces the cached one along with its validators.
+        let value: Vec<u32> = fetch_cached(&url, &cache_dir, &policy, &Mirrors::new())
+            .await
+            .unwrap();
+        assert_eq!(value, vec![2]);
+        let entry: CacheEntry = read_json(&path).await.unwrap();
+        assert_eq!(entry.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(entry.last_modified, None);
+        assert_eq!(entry.body, "[2]");
+
+        tokio::fs::remove_dir_all(&cache_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn serves_fresh_entries_without_requests() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("lyceris-cache-{}", uuid::Uuid::new_v4()));
+        let url = "http://unreachable.invalid/manifest.json";
+
+        write_json(
+            entry_path(&cache_dir, url),
+            &CacheEntry {
+                url: url.to_string(),
+                etag: Some("\"abc\"".to_string()),
+                last_modified: None,
+                validated_at: now(),
+                body: "[1,2,3]".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let policy = CachePolicy::default();
+        let value: Vec<u32> = fetch_cached(url, &cache_dir, &policy, &Mirrors::new())
+            .await
+            .unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+
+        // Stale entries are still served when the server can't be reached.
+        let policy = CachePolicy {
+            max_age: Duration::ZERO,
+            ..policy
+        };
+        let value: Vec<u32> = fetch_cached(url, &cache_dir, &policy, &Mirrors::new())
+            .await
+            .unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+
+        tokio::fs::remove_dir_all(&cache_dir).await.unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    path::PathBuf,
+    sync::{PoisonError, RwLock},
+    time::Duration,
+};
+
+use reqwest::{Certificate, Client, Proxy};
+use serde::{Deserialize, Serialize};
+
+/// The reqwest Client shared by fetch, the downloader and authentication,
+/// built from the default `HttpConfig` on first use unless one was configured.
+static CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+/// Settings of the shared HTTP client.
+///
+/// Without an explicit proxy the `HTTP_PROXY`, `HTTPS_PROXY` and
+/// `ALL_PROXY` environment variables are honoured.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Proxy used for every request, e.g. `http://proxy.school.lan:3128`
+    /// or `socks5h://127.0.0.1:1080`.
+    pub proxy: Option<String>,
+    /// User agent identifying the launcher.
+    pub user_agent: String,
+    /// PEM encoded certificate files trusted in addition to the system roots.
+    pub root_certificates: Vec<PathBuf>,
+    /// Maximum number of idle connections kept per host.
+    pub pool_max_idle_per_host: usize,
+    /// Maximum time to establish a connection, including the TLS handshake.
+    /// Applies to every request of the process, including the downloads of `install`.
+    pub connect_timeout: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            user_agent: format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            root_certificates: Vec::new(),
+            pool_max_idle_per_host: 32,
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn build_client(&self) -> crate::Result<Client> {
+        let mut builder = Client::builder()
+            .user_agent(&self.user_agent)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .connect_timeout(self.connect_timeout);
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        for path in &self.root_certificates {
+            let pem = std::fs::read(path)?;
+            for certificate in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Replaces the shared client with one built from the given settings.
+pub fn configure(config: &HttpConfig) -> crate::Result<()> {
+    set_client(config.build_client()?);
+    Ok(())
+}
+
+/// Replaces the shared client with a custom one.
+pub fn set_client(client: Client) {
+    *CLIENT.write().unwrap_or_else(PoisonError::into_inner) = Some(client);
+}
+
+/// Returns the shared client. Clones share the same connection pool.
+///
+/// # Errors
+///
+/// Returns the error of building the default client when none was configured,
+/// the next call tries to build it again.
+pub fn client() -> crate::Result<Client> {
+    if let Some(client) = CLIENT
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return Ok(client.clone());
+    }
+
+    let mut shared = CLIENT.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(client) = shared.as_ref() {
+        return Ok(client.clone());
+    }
+    let client = HttpConfig::default().build_client()?;
+    *shared = Some(client.clone());
+    Ok(client)
+}
@@ -1,28 +1,144 @@
 use futures::{stream, StreamExt};
-use reqwest::{Client, IntoUrl};
+use rand::Rng;
+use reqwest::{header::RANGE, IntoUrl, StatusCode};
+use serde::{Deserialize, Serialize};
 use std::{
-    path::Path,
-    sync::Arc,
-    time::{Duration, Instant},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use tokio::{
-    fs::{create_dir_all, File},
-    io::AsyncWriteExt,
+    fs::{create_dir_all, remove_file, rename, File, OpenOptions},
+    io::{AsyncReadExt, AsyncWriteExt},
     sync::Mutex,
     time::timeout,
 };
+use tokio_util::sync::CancellationToken;
 
+use super::{client::client, progress::ProgressTracker};
 use crate::{
     error::Error,
-    minecraft::emitter::{Emit, Emitter},
-    util::retry::retry,
+    minecraft::emitter::{Emit, Emitter, Event},
+    util::{
+        cancel::cancellable,
+        hash::{Checksum, Hasher},
+        retry::retry,
+    },
 };
 
+/// Concurrency, timeout and retry settings of the downloader.
+///
+/// The connect timeout isn't part of the policy: connections are made by the
+/// shared client, so it's set process-wide with `HttpConfig::connect_timeout`
+/// and `client::configure`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DownloadPolicy {
+    /// Maximum number of files downloaded at the same time by `download_multiple`.
+    pub max_concurrency: usize,
+    /// Maximum time to wait for the next chunk before the connection is considered dead.
+    pub chunk_timeout: Duration,
+    /// Maximum time from sending a request to receiving its response headers,
+    /// which includes connecting. The connect phase alone is bounded by
+    /// `HttpConfig::connect_timeout` of the shared client.
+    pub response_timeout: Duration,
+    /// Number of attempts per file before `download_multiple` gives up.
+    pub retries: u32,
+    /// Delay after the first failed attempt, doubled after every further failure.
+    pub backoff: Duration,
+    /// Upper bound of the backoff delay.
+    pub max_backoff: Duration,
+    /// Randomizes each delay between half and the full backoff,
+    /// so parallel downloads don't retry in lockstep.
+    pub jitter: bool,
+}
+
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 10,
+            chunk_timeout: Duration::from_secs(10),
+            response_timeout: Duration::from_secs(30),
+            retries: 3,
+            backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(60),
+            jitter: true,
+        }
+    }
+}
+
+impl DownloadPolicy {
+    /// Returns the delay to wait after the given failed attempt, starting from 1.
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+
+        if self.jitter && !delay.is_zero() {
+            delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+/// Expected hash and size of a downloaded file.
+///
+/// Empty hashes and zero sizes are treated as unknown and not verified.
+#[derive(Clone, Debug, Default)]
+pub struct Integrity {
+    pub checksum: Option<Checksum>,
+    pub size: Option<u64>,
+}
+
+impl Integrity {
+    pub fn new(sha1: impl Into<String>, size: u64) -> Self {
+        Self::with_checksum(Checksum::sha1(sha1), size)
+    }
+
+    pub fn with_checksum(checksum: Option<Checksum>, size: u64) -> Self {
+        Self {
+            checksum,
+            size: (size > 0).then_some(size),
+        }
+    }
+}
+
+/// A file to download with `download_multiple`.
+#[derive(Clone, Debug)]
+pub struct DownloadTask {
+    pub url: String,
+    /// URLs tried in order when downloading from `url` failed.
+    pub fallback_urls: Vec<String>,
+    pub destination: PathBuf,
+    pub integrity: Option<Integrity>,
+}
+
+/// Returns the temporary path a file is downloaded to before it's moved
+/// to its destination, e.g. `client.jar.part` for `client.jar`.
+pub fn part_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_default();
+    file_name.push(".part");
+    destination.with_file_name(file_name)
+}
+
 /// Downloads a file from the specified URL and saves it to the given destination.
 ///
 /// This function performs an asynchronous HTTP GET request to the provided URL,
-/// streams the response body, and writes the content to a file at the specified
-/// destination. It also provides progress updates through a callback function.
+/// streams the response body into a `.part` file next to the destination and
+/// renames it to the destination once the whole body is written. If a `.part`
+/// file already exists, the download is resumed with a `Range` request when the
+/// server supports it, otherwise it starts over. Progress is reported through
+/// the emitter as `Event::DownloadProgress` after each written chunk.
 ///
 /// # Parameters
 ///
@@ -30,16 +146,18 @@ use crate::{
 ///   the `IntoUrl` trait, such as a string slice or a `String`.
 /// - `destination`: A `PathBuf` representing the path where the downloaded file
 ///   will be saved.
-/// - `progression_callback`: A mutable closure that takes two `u64` parameters:
-///   the number of bytes downloaded so far and the total size of the file. This
-///   callback is called after each chunk of data is written to the file, allowing
-///   the caller to track the download progress.
+/// - `integrity`: The expected checksum and size of the file. The checksum can
+///   be SHA-1, SHA-256, SHA-512 or MD5 and is computed while streaming. On
+///   mismatch the partial file is removed and `Error::HashMismatch` or
+///   `Error::SizeMismatch` is returned.
+/// - `policy`: The timeouts applied to the request and to each received chunk.
+/// - `emitter`: An optional emitter the progress events are sent to.
+/// - `cancel`: An optional token that aborts the download when cancelled. The
+///   partially written file is removed and `Error::Cancelled` is returned.
 ///
 /// # Returns
 ///
-/// This function returns a `Result<u64, DownloaderError>`. On success, it returns
-/// `Ok(u64)`. If an error occurs during the download process, it returns an
-/// `Err` containing a `DownloaderError` that describes the failure.
+/// The number of bytes written to the destination, including the resumed part.
 ///
 /// # Errors
 ///
@@ -47,117 +165,375 @@ use crate::{
 /// - Network errors when making the HTTP request.
 /// - Non-success HTTP status codes (e.g., 404 Not Found).
 /// - Errors when creating or writing to the file.
+/// - No data received for `policy.chunk_timeout`, as `Error::Download`.
 pub async fn download<P: AsRef<Path>>(
     url: impl IntoUrl,
     destination: P,
+    integrity: Option<&Integrity>,
+    policy: &DownloadPolicy,
     emitter: Option<&Emitter>,
+    cancel: Option<&CancellationToken>,
 ) -> crate::Result<u64> {
-    // Send a get request to the given url.
-    let response = Client::builder().build()?.get(url).send().await?;
+    download_file(
+        url,
+        destination.as_ref(),
+        integrity,
+        policy,
+        emitter,
+        cancel,
+        None,
+    )
+    .await
+}
 
-    if !response.status().is_success() {
-        return Err(Error::Download(response.status().to_string()));
+/// Called with the number of bytes of the file received so far.
+type OnProgress<'a> = Option<&'a (dyn Fn(u64) + Sync)>;
+
+async fn download_file(
+    url: impl IntoUrl,
+    destination: &Path,
+    integrity: Option<&Integrity>,
+    policy: &DownloadPolicy,
+    emitter: Option<&Emitter>,
+    cancel: Option<&CancellationToken>,
+    on_progress: OnProgress<'_>,
+) -> crate::Result<u64> {
+    let part = PartialFile::new(part_path(destination));
+
+    match download_part(
+        url,
+        destination,
+        integrity,
+        policy,
+        emitter,
+        cancel,
+        on_progress,
+    )
+    .await
+    {
+        Ok(total_size) => {
+            rename(&part.path, destination).await?;
+            part.keep();
+            Ok(total_size)
+        }
+        // Dropping the guard removes the part file.
+        Err(e @ (Error::Cancelled | Error::HashMismatch { .. } | Error::SizeMismatch { .. })) => {
+            Err(e)
+        }
+        // The part file is kept so the next attempt can resume it.
+        Err(e) => {
+            part.keep();
+            Err(e)
+        }
     }
+}
 
-    // Get the total size of the file to use at progression
-    let total_size = response.content_length().unwrap_or(0);
-    let mut downloaded: u64 = 0;
+/// Removes the part file unless the download decided to keep it, so a download
+/// dropped halfway, e.g. by an aborted `download_multiple`, leaves nothing behind.
+struct PartialFile {
+    path: PathBuf,
+    armed: bool,
+}
+
+impl PartialFile {
+    fn new(path: PathBuf) -> Self {
+        Self { path, armed: true }
+    }
+
+    fn keep(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if self.armed {
+            std::fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+/// Downloads a file from the first URL that succeeds, trying them in order.
+///
+/// Cancellation is returned immediately, any other error moves on to the
+/// next URL. A partial file left by a failed URL is resumed from the next one,
+/// which is safe because the integrity is verified on completion.
+///
+/// # Errors
+///
+/// Returns the error of the last URL when all of them failed, or
+/// `Error::NotFound` when `urls` is empty.
+pub async fn download_with_fallbacks<P: AsRef<Path>>(
+    urls: &[String],
+    destination: P,
+    integrity: Option<&Integrity>,
+    policy: &DownloadPolicy,
+    emitter: Option<&Emitter>,
+    cancel: Option<&CancellationToken>,
+) -> crate::Result<u64> {
+    download_from(
+        urls,
+        destination.as_ref(),
+        integrity,
+        policy,
+        emitter,
+        cancel,
+        None,
+    )
+    .await
+}
+
+async fn download_from(
+    urls: &[String],
+    destination: &Path,
+    integrity: Option<&Integrity>,
+    policy: &DownloadPolicy,
+    emitter: Option<&Emitter>,
+    cancel: Option<&CancellationToken>,
+    on_progress: OnProgress<'_>,
+) -> crate::Result<u64> {
+    let mut last_error = None;
+
+    for url in urls {
+        match download_file(
+            url.as_str(),
+            destination,
+            integrity,
+            policy,
+            emitter,
+            cancel,
+            on_progress,
+        )
+        .await
+        {
+            Ok(total_size) => return Ok(total_size),
+            Err(Error::Cancelled) => return Err(Error::Cancelled),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| Error::NotFound(destination.display().to_string())))
+}
+
+async fn download_part(
+    url: impl IntoUrl,
+    destination: &Path,
+    integrity: Option<&Integrity>,
+    policy: &DownloadPolicy,
+    emitter: Option<&Emitter>,
+    cancel: Option<&CancellationToken>,
+    on_progress: OnProgress<'_>,
+) -> crate::Result<u64> {
+    let url = url.into_url()?;
+    let client = client()?;
+    let part_path = &part_path(destination);
 
-    if let Some(parent) = destination.as_ref().parent() {
+    if let Some(parent) = destination.parent() {
         if !parent.is_dir() {
             create_dir_all(parent).await?;
         }
     }
 
-    // Create a file to write the downloaded content
-    let mut file = File::create(&destination).await?;
+    let existing = tokio::fs::metadata(part_path)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+
+    let mut request = client.get(url.clone());
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+
+    // Send a get request to the given url.
+    let mut response =
+        cancellable(cancel, timeout(policy.response_timeout, request.send())).await???;
+
+    // The part file is already complete or no longer matches the remote file.
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        remove_file(part_path).await?;
+        response = cancellable(
+            cancel,
+            timeout(policy.response_timeout, client.get(url).send()),
+        )
+        .await???;
+    }
+
+    if !response.status().is_success() {
+        return Err(Error::Download(response.status().to_string()));
+    }
+
+    let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+
+    // Get the total size of the file to use at progression
+    let mut downloaded: u64 = if resumed { existing } else { 0 };
+    let total_size = response
+        .content_length()
+        .map(|length| length + downloaded)
+        .unwrap_or(0);
+    if let Some(on_progress) = on_progress {
+        on_progress(downloaded);
+    }
+
+    // Append to the part file when resuming, otherwise start it over
+    let mut hasher = integrity
+        .and_then(|integrity| integrity.checksum.as_ref())
+        .map(|checksum| Hasher::new(checksum.algorithm));
+    let mut file = if resumed {
+        if let Some(hasher) = &mut hasher {
+            let mut part = File::open(part_path).await?;
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let read = part.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+        }
+        OpenOptions::new().append(true).open(part_path).await?
+    } else {
+        File::create(part_path).await?
+    };
 
     // Stream the response body
     let mut stream = response.bytes_stream();
 
-    let mut last_data_received;
-
-    while let Some(chunk_result) = timeout(Duration::from_secs(10), stream.next()).await? {
+    while let Some(chunk_result) = cancellable(cancel, timeout(policy.chunk_timeout, stream.next()))
+        .await?
+        .map_err(|_| {
+            Error::Download(format!(
+                "Connection dead, no data for {} seconds.",
+                policy.chunk_timeout.as_secs_f64()
+            ))
+        })?
+    {
         match chunk_result {
             Ok(chunk) => {
-                // Reset the timer when data is received
-                last_data_received = Instant::now();
                 downloaded += chunk.len() as u64;
 
                 // Write chunk to the file
+                if let Some(hasher) = &mut hasher {
+                    hasher.update(&chunk);
+                }
                 file.write_all(&chunk).await?;
 
                 // Emit progress event
-                emitter
-                    .emit(
-                        "single_download_progress",
-                        (
-                            destination.as_ref().to_string_lossy().into_owned(),
-                            downloaded,
-                            total_size,
-                        ),
-                    )
-                    .await;
+                emitter.emit(Event::DownloadProgress {
+                    path: destination.to_path_buf(),
+                    downloaded,
+                    total: total_size,
+                });
+                if let Some(on_progress) = on_progress {
+                    on_progress(downloaded);
+                }
             }
-            Err(_) => {
-                // Timeout occurred (no chunk received in 3 seconds)
-                return Err(Error::Download(
-                    "Connection dead, no data for 3 seconds.".to_string(),
-                ));
+            Err(e) => {
+                // The connection broke while streaming the body
+                return Err(Error::Download(format!("Connection dead: {}", e)));
             }
         }
+    }
+
+    file.flush().await?;
 
-        // Check if no data has been received in the last 3 seconds
-        if last_data_received.elapsed() > Duration::from_secs(10) {
-            return Err(Error::Download(
-                "Connection dead, no data for 3 seconds.".to_string(),
-            ));
+    if let Some(integrity) = integrity {
+        if let Some(size) = integrity.size {
+            if size != downloaded {
+                return Err(Error::SizeMismatch {
+                    path: destination.to_path_buf(),
+                    expected: size,
+                    actual: downloaded,
+                });
+            }
+        }
+        if let (Some(checksum), Some(hasher)) = (&integrity.checksum, hasher) {
+            let actual = hasher.finalize();
+            if !actual.eq_ignore_ascii_case(&checksum.value) {
+                return Err(Error::HashMismatch {
+                    path: destination.to_path_buf(),
+                    expected: checksum.value.clone(),
+                    actual,
+                });
+            }
         }
     }
 
-    Ok(total_size)
+    Ok(downloaded)
 }
 
 /// Downloads multiple files from the specified URLs and saves them to the given destinations.
 ///
-/// This function takes a vector of tuples, where each tuple contains a URL and a destination path.
-/// It downloads all files in parallel and provides progress updates through a callback function.
+/// This function takes a vector of tasks, where each task contains a URL, a destination path and
+/// optionally the expected checksum and size of the file. It downloads all files in parallel,
+/// retries failed or corrupted ones, and reports progress through the emitter.
 ///
 /// # Parameters
 ///
-/// - `downloads`: A vector of tuples containing the URLs and their corresponding destination paths.
-/// - `progression_callback`: A mutable closure that takes four `u64` parameters:
-///   the number of bytes downloaded so far for the current file, the total bytes downloaded so far,
-///   the current file index, and the total number of files. This callback is called after each chunk of data
-///   is written to the file, allowing the caller to track the download progress.
+/// - `downloads`: A vector of tasks containing the URLs, their corresponding destination paths
+///   and expected integrity.
+/// - `policy`: The concurrency, timeouts and retry backoff applied to the downloads.
+/// - `emitter`: An optional emitter that receives `Event::DownloadProgress` for each chunk
+///   and `Event::FileDownloaded` with the completed and total file counts for each file.
+/// - `cancel`: An optional token that stops every pending download when cancelled.
+/// - `progress`: An optional tracker the received bytes of every file are added to.
 ///
 /// # Returns
 ///
-/// This function returns a `Result<(), HttpError>`. On success, it returns `Ok(())`. If an error occurs
-/// during the download process, it returns an `Err` containing a `HttpError` that describes the failure.
-pub async fn download_multiple<U, P>(
-    downloads: Vec<(U, P)>,
+/// `Ok(())` once every file is downloaded, otherwise the error of the first file that failed
+/// after all its retries, or `Error::Cancelled`.
+pub async fn download_multiple(
+    downloads: Vec<DownloadTask>,
+    policy: &DownloadPolicy,
     emitter: Option<&Emitter>,
-) -> crate::Result<()>
-where
-    U: IntoUrl + Send,               // URL type that implements IntoUrl
-    P: AsRef<Path> + Send + 'static, // Path type
-{
+    cancel: Option<&CancellationToken>,
+    progress: Option<&ProgressTracker>,
+) -> crate::Result<()> {
     let total_files = downloads.len();
     let total_downloaded = Arc::new(Mutex::new(0));
 
-    let tasks = downloads.into_iter().map(|(url, destination)| {
+    let tasks = downloads.into_iter().map(|task| {
         let total_downloaded = Arc::clone(&total_downloaded);
+        let urls: Vec<String> = std::iter::once(task.url.clone())
+            .chain(task.fallback_urls.iter().cloned())
+            .collect();
 
         async move {
-            // Retry download logic
-            let result = retry(
-                || async { download(url.as_str(), destination.as_ref(), emitter).await },
-                Result::is_ok,
-                3,
-                Duration::from_secs(5),
+            // Bytes of this file already added to the tracker, so restarts can take them back
+            let reported = AtomicU64::new(0);
+            let size_known = task
+                .integrity
+                .as_ref()
+                .is_some_and(|integrity| integrity.size.is_some());
+            let on_progress = |downloaded: u64| {
+                if let Some(progress) = progress {
+                    let previous = reported.swap(downloaded, Ordering::Relaxed);
+                    progress.advance(previous, downloaded, size_known, emitter);
+                }
+            };
+
+            // Retry download logic, a cancelled download is never retried
+            let result = cancellable(
+                cancel,
+                retry(
+                    || async {
+                        download_from(
+                            &urls,
+                            &task.destination,
+                            task.integrity.as_ref(),
+                            policy,
+                            emitter,
+                            cancel,
+                            Some(&on_progress),
+                        )
+                        .await
+                    },
+                    |result| result.is_ok() || matches!(result, Err(Error::Cancelled)),
+                    policy.retries.max(1),
+                    |attempt| policy.backoff_delay(attempt),
+                ),
             )
-            .await;
+            .await
+            .and_then(|result| result);
 
             // Check if the download was successful
             match result {
@@ -166,16 +542,11 @@ where
                     let mut downloaded = total_downloaded.lock().await;
                     *downloaded += 1;
 
-                    emitter
-                        .emit(
-                            "multiple_download_progress",
-                            (
-                                destination.as_ref().to_string_lossy().into_owned(),
-                                *downloaded as u64,
-                                total_files as u64,
-                            ),
-                        )
-                        .await;
+                    emitter.emit(Event::FileDownloaded {
+                        path: task.destination.clone(),
+                        completed: *downloaded,
+                        total: total_files,
+                    });
 
                     Ok::<(), Error>(())
                 }
@@ -188,7 +559,7 @@ where
     });
 
     // Create a stream of tasks with limited concurrency
-    let mut stream = stream::iter(tasks).buffered(10); // Limit concurrency here
+    let mut stream = stream::iter(tasks).buffered(policy.max_concurrency.max(1)); // Limit concurrency here
 
     // Poll the stream and handle results
     while let Some(result) = stream.next().await {
@@ -197,3 +568,198 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tokio_util::sync::CancellationToken;
+
+    use super::{download, download_multiple, part_path, DownloadPolicy, DownloadTask, Integrity};
+    use crate::error::Error;
+
+    /// SHA1 of `lyceris`.
+    pub(crate) const BODY_SHA1: &str = "218f21ba45c6279acaf24b8e661e9ee80586b084";
+
+    /// Serves `lyceris` with support for `Range` requests. With `stall` the
+    /// server sends the first bytes of the body and never the rest.
+    pub(crate) async fn serve(stall: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        let read = stream.read(&mut buffer).await.unwrap();
+                        if read == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buffer[..read]);
+                    }
+
+                    let body = b"lyceris";
+                    let start = String::from_utf8_lossy(&request)
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("range: bytes=")?
+                                .strip_suffix('-')?
+                                .parse()
+                                .ok()
+                        })
+                        .unwrap_or(0);
+                    let status = if start > 0 {
+                        "206 Partial Content"
+                    } else {
+                        "200 OK"
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len() - start
+                    );
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    if stall {
+                        stream.write_all(&body[start..start + 3]).await.unwrap();
+                        std::future::pending::<()>().await;
+                    }
+                    stream.write_all(&body[start..]).await.unwrap();
+                    stream.shutdown().await.ok();
+                });
+            }
+        });
+
+        format!("http://{}/lyceris", address)
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lyceris-download-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn resumes_partial_files() {
+        let url = serve(false).await;
+        let dir = temp_dir();
+        let destination = dir.join("file");
+        // Differs from the served bytes to tell a resumed download from a new one.
+        std::fs::write(part_path(&destination), b"LYC").unwrap();
+
+        let integrity = Integrity::with_checksum(None, 7);
+        let policy = DownloadPolicy::default();
+        let size = download(&url, &destination, Some(&integrity), &policy, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(size, 7);
+        assert_eq!(std::fs::read(&destination).unwrap(), b"LYCeris");
+        assert!(!part_path(&destination).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_the_chunk_timeout() {
+        let url = serve(true).await;
+        let dir = temp_dir();
+        let destination = dir.join("file");
+
+        let policy = DownloadPolicy {
+            chunk_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let result = download(&url, &destination, None, &policy, None, None).await;
+
+        let Err(Error::Download(message)) = result else {
+            panic!("expected a dead connection");
+        };
+        assert!(message.contains("no data for 0.1 seconds"), "{}", message);
+        // The received bytes are kept to resume from.
+        assert!(part_path(&destination).is_file());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn removes_files_with_a_wrong_hash() {
+        let url = serve(false).await;
+        let dir = temp_dir();
+        let destination = dir.join("file");
+
+        let integrity = Integrity::new("0".repeat(40), 7);
+        let policy = DownloadPolicy::default();
+        let result = download(&url, &destination, Some(&integrity), &policy, None, None).await;
+
+        assert!(matches!(result, Err(Error::HashMismatch { .. })));
+        assert!(!destination.exists());
+        assert!(!part_path(&destination).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancelled_downloads_leave_no_part_files() {
+        let url = serve(true).await;
+        let dir = temp_dir();
+        let destination = dir.join("file");
+
+        let cancel = CancellationToken::new();
+        let part = part_path(&destination);
+        let watcher = {
+            let cancel = cancel.clone();
+            let part = part.clone();
+            tokio::spawn(async move {
+                while std::fs::metadata(&part).map_or(true, |metadata| metadata.len() < 3) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                cancel.cancel();
+            })
+        };
+
+        let task = DownloadTask {
+            url,
+            fallback_urls: Vec::new(),
+            destination: destination.clone(),
+            integrity: Some(Integrity::new(BODY_SHA1, 7)),
+        };
+        let policy = DownloadPolicy::default();
+        let result = download_multiple(vec![task], &policy, None, Some(&cancel), None).await;
+        watcher.await.unwrap();
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(!destination.exists());
+        assert!(!part.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_limit() {
+        let policy = DownloadPolicy {
+            backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(30),
+            jitter: false,
+            ..Default::default()
+        };
+
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| policy.backoff_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![5, 10, 20, 30, 30]);
+
+        let jittered = DownloadPolicy {
+            jitter: true,
+            ..policy
+        }
+        .backoff_delay(2);
+        assert!(jittered >= Duration::from_secs(5) && jittered <= Duration::from_secs(10));
+    }
+}
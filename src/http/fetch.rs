@@ -1,10 +1,8 @@
-use once_cell::sync::Lazy;
 use reqwest::{IntoUrl, Response};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
 
-/// A global instance of the reqwest Client.
-static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+use super::client::client;
 
 /// A struct to hold optional fetch request parameters.
 #[derive(Default)]
@@ -20,6 +18,20 @@ pub async fn fetch<T: DeserializeOwned>(url: impl IntoUrl) -> crate::Result<T> {
     fetch_with_options::<T, ()>(url, None).await
 }
 
+/// Fetches the given url and returns the response body as text.
+///
+/// Useful for non-JSON documents such as Maven `maven-metadata.xml` files.
+/// Non-success status codes are returned as `Error::Download`.
+pub async fn fetch_text(url: impl IntoUrl) -> crate::Result<String> {
+    let response: Response = client()?.get(url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(crate::Error::Download(response.status().to_string()));
+    }
+
+    Ok(response.text().await?)
+}
+
 /// Performs a customizable fetch request.
 ///
 /// This function allows you to send HTTP requests with various options, including
@@ -57,7 +69,7 @@ pub async fn fetch_with_options<T: DeserializeOwned, B: Serialize + Default>(
 ) -> crate::Result<T> {
     let options = options.unwrap_or_default(); // Use default options if none provided
 
-    let mut request_builder = CLIENT.request(options.method.clone(), url);
+    let mut request_builder = client()?.request(options.method.clone(), url);
 
     // Add headers if provided
     for (key, value) in options.headers {
@@ -77,6 +89,10 @@ pub async fn fetch_with_options<T: DeserializeOwned, B: Serialize + Default>(
     // Send the request and await the response
     let response: Response = request_builder.send().await?;
 
+    if !response.status().is_success() {
+        return Err(crate::Error::Download(response.status().to_string()));
+    }
+
     // Deserialize the response body
     Ok(response.json::<T>().await?)
 }
@@ -0,0 +1,215 @@
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    downloader::{download_with_fallbacks, DownloadPolicy, DownloadTask, Integrity},
+    fetch::{fetch, fetch_text},
+};
+use crate::{error::Error, minecraft::emitter::Emitter};
+
+/// Redirects requests whose URL starts with `upstream` to the mirrors,
+/// which are tried in order before falling back to the upstream itself.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MirrorRule {
+    /// Base URL being mirrored, e.g. `https://libraries.minecraft.net`.
+    pub upstream: String,
+    /// Replacement bases, e.g. `http://cache.lan/libraries`.
+    pub mirrors: Vec<String>,
+    /// Whether the upstream is tried when every mirror failed.
+    #[serde(default = "default_fallback")]
+    pub fallback_to_upstream: bool,
+}
+
+fn default_fallback() -> bool {
+    true
+}
+
+/// Base URL overrides for upstream endpoints.
+///
+/// URLs are rewritten when a request is made, so every endpoint constant as
+/// well as the `url` fields inside fetched version metas, asset indexes and
+/// Java manifests go through the mirrors, while files cached on disk keep
+/// the upstream URLs and stay valid when the mirrors change.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Mirrors {
+    pub rules: Vec<MirrorRule>,
+}
+
+impl Mirrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule mirroring `upstream` to `mirrors`, tried in the given order.
+    pub fn mirror<S: Into<String>>(
+        mut self,
+        upstream: impl Into<String>,
+        mirrors: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.rules.push(MirrorRule {
+            upstream: upstream.into(),
+            mirrors: mirrors.into_iter().map(Into::into).collect(),
+            fallback_to_upstream: true,
+        });
+        self
+    }
+
+    /// Returns the URLs to try for the given upstream URL, in order.
+    ///
+    /// The longest matching upstream wins. Without a matching rule
+    /// only the URL itself is returned.
+    pub fn candidates(&self, url: &str) -> Vec<String> {
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| {
+                // Only match whole path segments, `https://a.com/lib` isn't an upstream of `https://a.com/library`.
+                url.strip_prefix(rule.upstream.trim_end_matches('/'))
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|rule| rule.upstream.trim_end_matches('/').len());
+
+        let Some(rule) = rule else {
+            return vec![url.to_string()];
+        };
+
+        let path = &url[rule.upstream.trim_end_matches('/').len()..];
+        let mut candidates: Vec<String> = rule
+            .mirrors
+            .iter()
+            .map(|mirror| format!("{}{}", mirror.trim_end_matches('/'), path))
+            .collect();
+
+        if rule.fallback_to_upstream || candidates.is_empty() {
+            candidates.push(url.to_string());
+        }
+
+        candidates
+    }
+
+    /// Builds a download task for the first candidate with the others as fallbacks.
+    pub fn task(
+        &self,
+        url: &str,
+        destination: PathBuf,
+        integrity: Option<Integrity>,
+    ) -> DownloadTask {
+        let mut urls = self.candidates(url).into_iter();
+        DownloadTask {
+            url: urls.next().unwrap_or_else(|| url.to_string()),
+            fallback_urls: urls.collect(),
+            destination,
+            integrity,
+        }
+    }
+
+    /// Fetches JSON from the first candidate that responds successfully.
+    pub async fn fetch<T: DeserializeOwned>(&self, url: &str) -> crate::Result<T> {
+        let mut last_error = None;
+        for candidate in self.candidates(url) {
+            match fetch(candidate).await {
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::NotFound(url.to_string())))
+    }
+
+    /// Fetches text from the first candidate that responds successfully.
+    pub async fn fetch_text(&self, url: &str) -> crate::Result<String> {
+        let mut last_error = None;
+        for candidate in self.candidates(url) {
+            match fetch_text(candidate).await {
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::NotFound(url.to_string())))
+    }
+
+    /// Downloads a single file through the mirrors.
+    pub async fn download(
+        &self,
+        url: &str,
+        destination: &Path,
+        integrity: Option<&Integrity>,
+        policy: &DownloadPolicy,
+        emitter: Option<&Emitter>,
+        cancel: Option<&CancellationToken>,
+    ) -> crate::Result<u64> {
+        download_with_fallbacks(
+            &self.candidates(url),
+            destination,
+            integrity,
+            policy,
+            emitter,
+            cancel,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mirrors;
+
+    #[test]
+    fn rewrites_to_mirrors_in_order() {
+        let mirrors = Mirrors::new()
+            .mirror("https://piston-meta.mojang.com", ["http://cache.lan/meta/"])
+            .mirror(
+                "https://piston-meta.mojang.com/v1/packages",
+                [
+                    "http://cache.lan/packages",
+                    "https://bmclapi2.bangbang93.com/v1/packages",
+                ],
+            );
+
+        assert_eq!(
+            mirrors.candidates("https://piston-meta.mojang.com/v1/packages/abc/1.21.4.json"),
+            vec![
+                "http://cache.lan/packages/abc/1.21.4.json",
+                "https://bmclapi2.bangbang93.com/v1/packages/abc/1.21.4.json",
+                "https://piston-meta.mojang.com/v1/packages/abc/1.21.4.json",
+            ]
+        );
+        assert_eq!(
+            mirrors.candidates("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json")
+                [0],
+            "http://cache.lan/meta/mc/game/version_manifest_v2.json"
+        );
+        assert_eq!(
+            mirrors.candidates("https://libraries.minecraft.net/a.jar"),
+            vec!["https://libraries.minecraft.net/a.jar"]
+        );
+    }
+
+    #[test]
+    fn only_matches_whole_path_segments() {
+        let mirrors = Mirrors::new()
+            .mirror("https://a.com/lib", ["http://cache.lan/lib"])
+            .mirror(
+                "https://libraries.minecraft.net",
+                ["http://cache.lan/libraries"],
+            );
+
+        assert_eq!(
+            mirrors.candidates("https://a.com/library/x"),
+            vec!["https://a.com/library/x"]
+        );
+        assert_eq!(
+            mirrors.candidates("https://a.com/lib/x")[0],
+            "http://cache.lan/lib/x"
+        );
+        assert_eq!(
+            mirrors.candidates("https://libraries.minecraft.net.evil/a.jar"),
+            vec!["https://libraries.minecraft.net.evil/a.jar"]
+        );
+        assert_eq!(
+            mirrors.candidates("https://libraries.minecraft.net/a.jar")[0],
+            "http://cache.lan/libraries/a.jar"
+        );
+    }
+}
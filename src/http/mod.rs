@@ -1,2 +1,6 @@
+pub mod cache;
+pub mod client;
 pub mod downloader;
-pub mod fetch;
\ No newline at end of file
+pub mod fetch;
+pub mod mirror;
+pub mod progress;
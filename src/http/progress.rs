@@ -0,0 +1,175 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::minecraft::emitter::{Emit, Emitter, Event, Phase, Progress};
+
+/// Minimum time between two `Event::Progress` events.
+const EMIT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Weight of the latest sample in the smoothed throughput.
+const SMOOTHING: f64 = 0.3;
+
+/// Aggregates the bytes received by every download of an install
+/// and reports them through `Event::Progress`.
+///
+/// The totals are the sizes known up front, downloads without a known
+/// size add the bytes they receive to the total as they go.
+pub struct ProgressTracker {
+    total: AtomicU64,
+    downloaded: AtomicU64,
+    phase_total: AtomicU64,
+    phase_downloaded: AtomicU64,
+    state: Mutex<State>,
+}
+
+struct State {
+    phase: Phase,
+    last_emitted: Instant,
+    last_downloaded: u64,
+    bytes_per_second: f64,
+}
+
+impl ProgressTracker {
+    /// Creates a tracker expecting `total` bytes, counted in `Phase::Download` until another phase starts.
+    pub fn new(total: u64) -> Self {
+        Self {
+            total: AtomicU64::new(total),
+            downloaded: AtomicU64::new(0),
+            phase_total: AtomicU64::new(0),
+            phase_downloaded: AtomicU64::new(0),
+            state: Mutex::new(State {
+                phase: Phase::Download,
+                last_emitted: Instant::now(),
+                last_downloaded: 0,
+                bytes_per_second: 0.0,
+            }),
+        }
+    }
+
+    /// Starts counting the bytes of a new phase with the given expected size.
+    pub fn start_phase(&self, phase: Phase, total: u64) {
+        self.phase_total.store(total, Ordering::Relaxed);
+        self.phase_downloaded.store(0, Ordering::Relaxed);
+        self.lock().phase = phase;
+    }
+
+    /// Records that a file went from `previous` to `current` received bytes.
+    ///
+    /// `current` may be lower than `previous` when a download starts over.
+    pub(crate) fn advance(
+        &self,
+        previous: u64,
+        current: u64,
+        size_known: bool,
+        emitter: Option<&Emitter>,
+    ) {
+        let counters: &[&AtomicU64] = if size_known {
+            &[&self.downloaded, &self.phase_downloaded]
+        } else {
+            &[
+                &self.downloaded,
+                &self.phase_downloaded,
+                &self.total,
+                &self.phase_total,
+            ]
+        };
+        for counter in counters {
+            if current >= previous {
+                counter.fetch_add(current - previous, Ordering::Relaxed);
+            } else {
+                counter.fetch_sub(previous - current, Ordering::Relaxed);
+            }
+        }
+
+        self.emit(emitter, false);
+    }
+
+    /// Emits the current progress, `force` skips the rate limit.
+    pub fn emit(&self, emitter: Option<&Emitter>, force: bool) {
+        if emitter.is_none() {
+            return;
+        }
+
+        let progress = {
+            let mut state = self.lock();
+            let elapsed = state.last_emitted.elapsed();
+            if !force && elapsed < EMIT_INTERVAL {
+                return;
+            }
+
+            let downloaded = self.downloaded.load(Ordering::Relaxed);
+            let total = self.total.load(Ordering::Relaxed).max(downloaded);
+            if !elapsed.is_zero() {
+                let sample =
+                    downloaded.saturating_sub(state.last_downloaded) as f64 / elapsed.as_secs_f64();
+                state.bytes_per_second = if state.bytes_per_second == 0.0 {
+                    sample
+                } else {
+                    SMOOTHING * sample + (1.0 - SMOOTHING) * state.bytes_per_second
+                };
+            }
+            state.last_emitted = Instant::now();
+            state.last_downloaded = downloaded;
+
+            let phase_downloaded = self.phase_downloaded.load(Ordering::Relaxed);
+            Progress {
+                phase: state.phase,
+                phase_downloaded,
+                phase_total: self
+                    .phase_total
+                    .load(Ordering::Relaxed)
+                    .max(phase_downloaded),
+                downloaded,
+                total,
+                bytes_per_second: state.bytes_per_second as u64,
+                eta: (state.bytes_per_second >= 1.0).then(|| {
+                    Duration::from_secs_f64((total - downloaded) as f64 / state.bytes_per_second)
+                }),
+            }
+        };
+
+        emitter.emit(Event::Progress(progress));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProgressTracker;
+    use crate::minecraft::emitter::{Emitter, Event, Phase};
+
+    #[test]
+    fn aggregates_bytes_across_restarts_and_phases() {
+        let emitter = Emitter::default();
+        let mut events = emitter.channel();
+        let progress = ProgressTracker::new(300);
+
+        progress.start_phase(Phase::Client, 100);
+        progress.advance(0, 60, true, None);
+        // The download started over after receiving 60 bytes.
+        progress.advance(60, 0, true, None);
+        progress.advance(0, 100, true, None);
+        progress.start_phase(Phase::Download, 200);
+        progress.advance(0, 50, true, None);
+        // A file without a known size grows the totals.
+        progress.advance(0, 10, false, None);
+        progress.emit(Some(&emitter), true);
+
+        let Ok(Event::Progress(progress)) = events.try_recv() else {
+            panic!("expected a progress event");
+        };
+        assert_eq!(progress.phase, Phase::Download);
+        assert_eq!((progress.downloaded, progress.total), (160, 310));
+        assert_eq!((progress.phase_downloaded, progress.phase_total), (60, 210));
+    }
+}
@@ -30,7 +30,7 @@ pub struct Version {
     pub released: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct JavaFileManifest {
     pub files: HashMap<String, File>,
 }
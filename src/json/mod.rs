@@ -1,2 +1,2 @@
+pub mod java;
 pub mod version;
-pub mod java;
\ No newline at end of file
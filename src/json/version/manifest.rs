@@ -1,4 +1,4 @@
-use serde::{Serialize, Deserialize};
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub struct VersionManifest {
@@ -18,7 +18,7 @@ pub struct Latest {
     pub snapshot: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Version {
     pub id: String,
@@ -28,7 +28,7 @@ pub struct Version {
     pub release_time: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Type {
     #[serde(rename = "old_alpha")]
     OldAlpha,
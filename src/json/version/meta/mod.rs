@@ -1,2 +1,2 @@
+pub mod custom;
 pub mod vanilla;
-pub mod custom;
\ No newline at end of file
@@ -2,7 +2,10 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::minecraft::loader::forge::{Data, Processor};
+use crate::{
+    minecraft::loader::forge::{Data, Processor},
+    util::hash::Checksum,
+};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -31,7 +34,7 @@ pub struct VersionMeta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub processors: Option<Vec<Processor>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<HashMap<String, Data>>
+    pub data: Option<HashMap<String, Data>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -107,15 +110,34 @@ pub struct Downloads {
     pub server_mappings: Option<File>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct File {
     pub sha1: String,
     pub size: i64,
     pub url: String,
     pub path: Option<String>,
+    /// Hashes carried over from loader libraries, which don't always have a SHA1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+}
+
+impl File {
+    /// Returns the strongest known hash of the file.
+    pub fn checksum(&self) -> Option<Checksum> {
+        Checksum::strongest(
+            Some(&self.sha1),
+            self.sha256.as_deref(),
+            self.sha512.as_deref(),
+            self.md5.as_deref(),
+        )
+    }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct JavaVersion {
     #[serde(default = "default_java_version")]
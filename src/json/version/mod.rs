@@ -1,3 +1,3 @@
+pub mod asset_index;
 pub mod manifest;
 pub mod meta;
-pub mod asset_index;
\ No newline at end of file
@@ -28,11 +28,11 @@ mod tests {
             current_dir.join("target").join("game"),
             "1.16.5",
             AuthMethod::Offline {
-                username: "Miate",
+                username: "Miate".into(),
                 uuid: None,
             },
         )
-        .loader(Forge("36.2.42"))
+        .loader(Forge("36.2.42".into()))
         .build();
 
         let emitter = Emitter::default();
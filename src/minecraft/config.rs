@@ -3,95 +3,124 @@ use std::path::{Path, PathBuf};
 #[cfg(not(target_os = "windows"))]
 use std::os::unix::fs::PermissionsExt;
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
-use crate::{auth::AuthMethod, json::version::meta::vanilla::JavaVersion};
+use crate::{
+    auth::AuthMethod,
+    http::{
+        cache::{fetch_cached, CachePolicy, MetadataSource},
+        downloader::DownloadPolicy,
+        mirror::Mirrors,
+    },
+    json::version::meta::vanilla::JavaVersion,
+};
 
-use super::loader::Loader;
+use super::loader::LoaderKind;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Memory {
     Megabyte(u64),
     Gigabyte(u16),
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Config<T: Loader> {
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Config {
     pub game_dir: PathBuf,
-    pub version: &'static str,
+    pub version: String,
     pub authentication: AuthMethod,
     pub memory: Option<Memory>,
-    pub version_name: Option<&'static str>,
-    pub loader: Option<T>,
-    pub java_version: Option<&'static str>,
+    pub version_name: Option<String>,
+    #[serde(default)]
+    pub loader: LoaderKind,
+    pub java_version: Option<String>,
     pub runtime_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub shared_dir: Option<PathBuf>,
     pub custom_java_args: Vec<String>,
     pub custom_args: Vec<String>,
+    #[serde(default)]
+    pub download_policy: DownloadPolicy,
+    #[serde(default)]
+    pub mirrors: Mirrors,
+    #[serde(default)]
+    pub metadata_cache: CachePolicy,
+    /// Installs from the cached metadata only, without touching the network.
+    #[serde(default)]
+    pub offline: bool,
+    /// Makes `install` hash every file again instead of trusting the verification cache.
+    #[serde(default)]
+    pub force_verification: bool,
+    #[serde(skip)]
+    pub cancellation_token: Option<CancellationToken>,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ConfigBuilder<T: Loader = ()> {
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigBuilder {
     game_dir: PathBuf,
-    version: &'static str,
+    version: String,
     authentication: AuthMethod,
     memory: Option<Memory>,
-    version_name: Option<&'static str>,
-    loader: Option<T>,
-    java_version: Option<&'static str>,
+    version_name: Option<String>,
+    loader: LoaderKind,
+    java_version: Option<String>,
     runtime_dir: Option<PathBuf>,
+    shared_dir: Option<PathBuf>,
     custom_java_args: Vec<String>,
     custom_args: Vec<String>,
+    download_policy: DownloadPolicy,
+    mirrors: Mirrors,
+    metadata_cache: CachePolicy,
+    offline: bool,
+    force_verification: bool,
+    #[serde(skip)]
+    cancellation_token: Option<CancellationToken>,
 }
 
-impl ConfigBuilder<()> {
+impl ConfigBuilder {
     pub fn new<T: AsRef<Path>>(
         game_dir: T,
-        version: &'static str,
+        version: impl Into<String>,
         authentication: AuthMethod,
-    ) -> ConfigBuilder<()> {
+    ) -> ConfigBuilder {
         ConfigBuilder {
             game_dir: game_dir.as_ref().to_path_buf(),
-            version,
+            version: version.into(),
             authentication,
             memory: None,
             version_name: None,
-            loader: None,
+            loader: LoaderKind::Vanilla,
             java_version: None,
             runtime_dir: None,
+            shared_dir: None,
             custom_java_args: Vec::new(),
             custom_args: Vec::new(),
+            download_policy: DownloadPolicy::default(),
+            mirrors: Mirrors::default(),
+            metadata_cache: CachePolicy::default(),
+            offline: false,
+            force_verification: false,
+            cancellation_token: None,
         }
     }
-}
 
-impl<T: Loader> ConfigBuilder<T> {
     pub fn memory(mut self, memory: Memory) -> Self {
         self.memory = Some(memory);
         self
     }
 
-    pub fn version_name(mut self, version_name: &'static str) -> Self {
-        self.version_name = Some(version_name);
+    pub fn version_name(mut self, version_name: impl Into<String>) -> Self {
+        self.version_name = Some(version_name.into());
         self
     }
 
-    pub fn loader<C: Loader>(self, loader: C) -> ConfigBuilder<C> {
-        ConfigBuilder {
-            game_dir: self.game_dir,
-            version: self.version,
-            authentication: self.authentication,
-            memory: self.memory,
-            version_name: self.version_name,
-            loader: Some(loader),
-            java_version: self.java_version,
-            runtime_dir: self.runtime_dir,
-            custom_java_args: self.custom_java_args,
-            custom_args: self.custom_args,
-        }
+    pub fn loader(mut self, loader: impl Into<LoaderKind>) -> Self {
+        self.loader = loader.into();
+        self
     }
 
-    pub fn java_version(mut self, java_version: &'static str) -> Self {
-        self.java_version = Some(java_version);
+    pub fn java_version(mut self, java_version: impl Into<String>) -> Self {
+        self.java_version = Some(java_version.into());
         self
     }
 
@@ -100,6 +129,13 @@ impl<T: Loader> ConfigBuilder<T> {
         self
     }
 
+    /// Sets the directory holding `libraries`, `assets`, `natives`, `runtimes`
+    /// and `versions`, so multiple game directories can share them.
+    pub fn shared_dir(mut self, shared_dir: PathBuf) -> Self {
+        self.shared_dir = Some(shared_dir);
+        self
+    }
+
     pub fn custom_java_args(mut self, custom_java_args: Vec<String>) -> Self {
         self.custom_java_args = custom_java_args;
         self
@@ -110,7 +146,50 @@ impl<T: Loader> ConfigBuilder<T> {
         self
     }
 
-    pub fn build(self) -> Config<T> {
+    /// Sets the concurrency, timeouts and retry policy used for downloads during `install`.
+    ///
+    /// The connect timeout is shared by every config and set with
+    /// `http::client::configure` instead, see `HttpConfig::connect_timeout`.
+    pub fn download_policy(mut self, download_policy: DownloadPolicy) -> Self {
+        self.download_policy = download_policy;
+        self
+    }
+
+    /// Sets the mirrors requests to upstream endpoints are redirected to during `install`.
+    pub fn mirrors(mut self, mirrors: Mirrors) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Sets how long fetched manifests are reused before they're revalidated.
+    pub fn metadata_cache(mut self, metadata_cache: CachePolicy) -> Self {
+        self.metadata_cache = metadata_cache;
+        self
+    }
+
+    /// Makes `install` verify the files against the cached version JSON,
+    /// asset index and Java file manifest instead of fetching anything.
+    /// Missing or corrupt files are reported with `Error::MissingFiles`.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Makes `install` hash every file again, ignoring the verification cache
+    /// of files whose size and modification time didn't change. `verify` and
+    /// `repair` always hash every file.
+    pub fn force_verification(mut self, force_verification: bool) -> Self {
+        self.force_verification = force_verification;
+        self
+    }
+
+    /// Sets a token that aborts `install` and `launch` when cancelled.
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    pub fn build(self) -> Config {
         Config {
             game_dir: self.game_dir,
             version: self.version,
@@ -120,41 +199,61 @@ impl<T: Loader> ConfigBuilder<T> {
             loader: self.loader,
             java_version: self.java_version,
             runtime_dir: self.runtime_dir,
+            shared_dir: self.shared_dir,
             custom_java_args: self.custom_java_args,
             custom_args: self.custom_args,
+            download_policy: self.download_policy,
+            mirrors: self.mirrors,
+            metadata_cache: self.metadata_cache,
+            offline: self.offline,
+            force_verification: self.force_verification,
+            cancellation_token: self.cancellation_token,
         }
     }
 }
 
-impl<T: Loader> Config<T> {
-    pub fn new(game_dir: PathBuf, version: &'static str, authentication: AuthMethod) -> Self {
+impl Config {
+    pub fn new(game_dir: PathBuf, version: String, authentication: AuthMethod) -> Self {
         Self {
             game_dir,
             version,
             authentication,
             memory: None,
             version_name: None,
-            loader: None,
+            loader: LoaderKind::Vanilla,
             java_version: None,
             runtime_dir: None,
+            shared_dir: None,
             custom_java_args: Vec::new(),
             custom_args: Vec::new(),
+            download_policy: DownloadPolicy::default(),
+            mirrors: Mirrors::default(),
+            metadata_cache: CachePolicy::default(),
+            offline: false,
+            force_verification: false,
+            cancellation_token: None,
         }
     }
 
     pub fn get_version_name(&self) -> String {
         self.version_name
-            .map(|name| name.to_owned())
+            .clone()
             .or_else(|| {
                 self.loader
-                    .as_ref()
-                    .map(|loader| format!("{}-{}", self.version, loader.get_version()))
+                    .get_version()
+                    .map(|loader_version| format!("{}-{}", self.version, loader_version))
             })
-            .unwrap_or_else(|| self.version.to_string())
+            .unwrap_or_else(|| self.version.clone())
+    }
+
+    /// Returns the directory shared files are stored in,
+    /// which is the game directory unless a shared directory is set.
+    pub fn get_shared_path(&self) -> &Path {
+        self.shared_dir.as_deref().unwrap_or(&self.game_dir)
     }
 
     pub fn get_libraries_path(&self) -> PathBuf {
-        self.game_dir.join("libraries")
+        self.get_shared_path().join("libraries")
     }
 
     pub async fn get_java_path(&self, version: &JavaVersion) -> crate::Result<PathBuf> {
@@ -185,22 +284,42 @@ impl<T: Loader> Config<T> {
         Ok(java_path)
     }
 
+    /// Returns the mirrors and metadata cache of this config, e.g. for `list_versions`.
+    pub fn metadata_source(&self) -> MetadataSource {
+        MetadataSource {
+            mirrors: self.mirrors.clone(),
+            cache_dir: self.get_metadata_cache_path(),
+            cache_policy: self.metadata_cache.clone(),
+        }
+    }
+
+    /// Fetches a manifest through the mirrors and the on-disk metadata cache.
+    pub async fn fetch_metadata<T: DeserializeOwned>(&self, url: &str) -> crate::Result<T> {
+        fetch_cached(
+            url,
+            &self.get_metadata_cache_path(),
+            &self.metadata_cache,
+            &self.mirrors,
+        )
+        .await
+    }
+
     pub fn get_versions_path(&self) -> PathBuf {
-        self.game_dir.join("versions")
+        self.get_shared_path().join("versions")
     }
 
     pub fn get_assets_path(&self) -> PathBuf {
-        self.game_dir.join("assets")
+        self.get_shared_path().join("assets")
     }
 
     pub fn get_natives_path(&self) -> PathBuf {
-        self.game_dir.join("natives")
+        self.get_shared_path().join("natives")
     }
 
     pub fn get_runtime_path(&self) -> PathBuf {
         self.runtime_dir
             .clone()
-            .unwrap_or_else(|| self.game_dir.join("runtimes"))
+            .unwrap_or_else(|| self.get_shared_path().join("runtimes"))
     }
 
     pub fn get_indexes_path(&self) -> PathBuf {
@@ -216,8 +335,52 @@ impl<T: Loader> Config<T> {
             .join(format!("{}.json", self.get_version_name()))
     }
 
+    /// Returns the directory fetched manifests are cached in.
+    pub fn get_metadata_cache_path(&self) -> PathBuf {
+        self.get_shared_path().join(".cache").join("metadata")
+    }
+
+    /// Returns the path of the cache of files that were already verified.
+    pub fn get_verification_cache_path(&self) -> PathBuf {
+        self.get_shared_path()
+            .join(".cache")
+            .join("verification.json")
+    }
+
+    /// Returns the path the Java file manifest of the given runtime component is cached at.
+    pub fn get_java_manifest_path(&self, component: &str) -> PathBuf {
+        self.get_runtime_path().join(format!("{}.json", component))
+    }
+
     pub fn get_version_jar_path(&self) -> PathBuf {
         self.get_version_path()
             .join(format!("{}.jar", self.get_version_name()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConfigBuilder, Memory};
+    use crate::{auth::AuthMethod, minecraft::loader::fabric::Fabric};
+
+    #[test]
+    fn config_round_trips_through_json() {
+        let username = String::from("Lyceris");
+        let config = ConfigBuilder::new(
+            "game",
+            String::from("1.21.4"),
+            AuthMethod::Offline {
+                username,
+                uuid: None,
+            },
+        )
+        .memory(Memory::Gigabyte(4))
+        .loader(Fabric("0.16.9".into()))
+        .build();
+
+        let json = serde_json::to_string(&config).unwrap();
+        let config: Config = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(config.get_version_name(), "1.21.4-0.16.9");
+    }
+}
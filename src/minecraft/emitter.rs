@@ -1,36 +1,173 @@
-use event_emitter_rs::EventEmitter;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// Steps of `install`, reported through `Event::PhaseStarted` and `Event::PhaseFinished`.
+///
+/// `Loader` only runs inside `Metadata` when the version JSON isn't cached yet.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Fetching the version meta, asset index and Java file manifest.
+    Metadata,
+    /// Merging the mod loader into the version meta.
+    Loader,
+    /// Downloading the client jar.
+    Client,
+    /// Checking the hashes of the files already on disk.
+    Verification,
+    /// Downloading the missing files of the Java runtime.
+    JavaRuntime,
+    /// Downloading the missing libraries and assets.
+    Download,
+    /// Extracting the native libraries.
+    Natives,
+    /// Running the processors of installer based loaders.
+    Processors,
+}
+
+/// Kinds of files `install` downloads.
+///
+/// Libraries added by mod loaders are counted as `Library`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    Client,
+    Asset,
+    Library,
+    Native,
+    JavaRuntime,
+}
+
+/// Number and size of the files of one kind `install` is about to download.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadTotal {
+    pub kind: FileKind,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Aggregate download progress of `install`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Progress {
+    /// The phase currently downloading.
+    pub phase: Phase,
+    /// Bytes received and expected in the current phase.
+    pub phase_downloaded: u64,
+    pub phase_total: u64,
+    /// Bytes received and expected in the whole install.
+    pub downloaded: u64,
+    pub total: u64,
+    /// Smoothed download speed.
+    pub bytes_per_second: u64,
+    /// Estimated time until every download is finished, unknown until data arrived.
+    pub eta: Option<Duration>,
+}
+
+/// Progress and output reported by `install` and `launch`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Event {
+    PhaseStarted(Phase),
+    PhaseFinished(Phase),
+    /// Everything `install` is about to download, sent once after the verification.
+    DownloadPlan(Vec<DownloadTotal>),
+    /// Bytes received across all downloads, sent a few times a second.
+    Progress(Progress),
+    /// Bytes received of a single file, `total` is 0 when the server didn't send a length.
+    DownloadProgress {
+        path: PathBuf,
+        downloaded: u64,
+        total: u64,
+    },
+    /// A file of a batch download finished.
+    FileDownloaded {
+        path: PathBuf,
+        completed: usize,
+        total: usize,
+    },
+    /// A file on disk was checked against its expected hash.
+    FileVerified {
+        path: PathBuf,
+        checked: usize,
+        total: usize,
+    },
+    /// A natives jar was extracted.
+    Extracted {
+        path: PathBuf,
+    },
+    /// A loader processor started, `current` counts from 1.
+    Processor {
+        current: usize,
+        total: usize,
+    },
+    /// A line printed by the game.
+    GameLog(String),
+    /// The game process exited, `code` is `None` when it was killed by a signal.
+    GameExited {
+        code: Option<i32>,
+    },
+}
+
+/// Receives the events of an `Emitter`.
+///
+/// Listeners are called on the thread that emitted the event, which may be a
+/// download task or a blocking hashing thread, so they should return quickly.
+/// Use `Emitter::channel` to handle events somewhere else.
+pub trait Listener: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F: Fn(&Event) + Send + Sync> Listener for F {
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
 
 #[derive(Clone, Default)]
 pub struct Emitter {
-    pub wrap: Arc<Mutex<EventEmitter>>,
+    listeners: Arc<RwLock<Vec<Arc<dyn Listener>>>>,
 }
 
 pub trait Emit {
-    #[allow(async_fn_in_trait)]
-    async fn emit<T: Serialize>(&self, event_name: &str, data: T);
+    fn emit(&self, event: Event);
 }
 
 impl Emit for Option<&Emitter> {
-    async fn emit<T: Serialize>(&self, event_name: &str, data: T) {
+    fn emit(&self, event: Event) {
         if let Some(emitter) = self {
-            emitter.wrap.lock().await.emit(event_name, data);
+            emitter.emit(event);
         }
     }
 }
 
 impl Emitter {
-    pub async fn emit<T: Serialize>(&self, event_name: &str, data: T) {
-        self.wrap.lock().await.emit(event_name, data);
+    pub fn emit(&self, event: Event) {
+        let listeners = self
+            .listeners
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for listener in listeners.iter() {
+            listener.on_event(&event);
+        }
+    }
+
+    /// Registers a listener that's called with every emitted event.
+    pub fn on(&self, listener: impl Listener + 'static) {
+        self.listeners
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Arc::new(listener));
     }
 
-    pub async fn on<F, T>(&self, event_name: &str, listener: F)
-    where
-        F: Fn(T) + Send + Sync + 'static,
-        T: for<'de> Deserialize<'de> + Serialize,
-    {
-        self.wrap.lock().await.on(event_name, listener);
+    /// Returns a receiver every emitted event is sent to.
+    pub fn channel(&self) -> UnboundedReceiver<Event> {
+        let (sender, receiver) = unbounded_channel();
+        self.on(move |event: &Event| {
+            // The receiver may have been dropped, which just stops the delivery.
+            sender.send(event.clone()).ok();
+        });
+        receiver
     }
 }
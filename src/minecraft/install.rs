@@ -1,16 +1,21 @@
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     env::consts::{ARCH, OS},
     fs,
     path::{Path, PathBuf, MAIN_SEPARATOR_STR},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 use tokio::{fs::create_dir_all, process::Command};
 
 use crate::{
     error::Error,
     http::{
-        downloader::{download, download_multiple},
-        fetch::fetch,
+        downloader::{download_multiple, DownloadTask, Integrity},
+        progress::ProgressTracker,
     },
     json::{
         java::{JavaFileManifest, JavaManifest},
@@ -24,46 +29,288 @@ use crate::{
         CLASSPATH_SEPARATOR, JAVA_MANIFEST_ENDPOINT, RESOURCES_ENDPOINT, VERSION_MANIFEST_ENDPOINT,
     },
     util::{
+        cancel::{cancellable, check_cancelled},
         extract::{extract_file, read_file_from_jar},
-        hash::calculate_sha1,
+        hash::Checksum,
         json::{read_json, write_json},
+        verification::VerificationCache,
     },
 };
 
 use super::{
     config::Config,
-    emitter::Emitter,
-    loader::Loader,
+    emitter::{DownloadTotal, Emit, Emitter, Event, FileKind, Phase},
     parse::{parse_lib_path, ParseRule},
 };
 
-#[derive(Clone)]
-enum FileType {
+/// What a file of the install is used for.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum FileType {
+    Client,
     Asset { is_virtual: bool, is_map: bool },
     Library,
+    Native,
     Java,
 }
 
-#[derive(Clone)]
-struct DownloadFile {
-    file_name: String,
-    sha1: String,
-    url: String,
-    path: PathBuf,
-    r#type: FileType,
+impl FileType {
+    pub fn kind(&self) -> FileKind {
+        match self {
+            FileType::Client => FileKind::Client,
+            FileType::Asset { .. } => FileKind::Asset,
+            FileType::Library => FileKind::Library,
+            FileType::Native => FileKind::Native,
+            FileType::Java => FileKind::JavaRuntime,
+        }
+    }
+
+    fn phase(&self) -> Phase {
+        match self {
+            FileType::Client => Phase::Client,
+            FileType::Java => Phase::JavaRuntime,
+            _ => Phase::Download,
+        }
+    }
+}
+
+/// A file of the install with where it comes from and its expected hash.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadFile {
+    pub file_name: String,
+    /// The strongest hash the metadata has for the file, if any.
+    pub checksum: Option<Checksum>,
+    pub size: u64,
+    pub url: String,
+    pub path: PathBuf,
+    pub r#type: FileType,
+    /// Whether the file is missing or corrupt and will be downloaded.
+    pub download: bool,
+}
+
+/// The Java runtime the version runs on.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JavaRuntime {
+    pub component: String,
+    pub path: PathBuf,
+}
+
+/// Everything `execute_plan` does to install a version, created by `plan_install`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstallPlan {
+    pub version_name: String,
+    /// Every file of the version, with `download` set on the ones to fetch.
+    pub files: Vec<DownloadFile>,
+    /// Natives jars extracted into `natives_path`.
+    pub natives: Vec<PathBuf>,
+    pub natives_path: PathBuf,
+    /// Whether the assets are copied to where legacy versions expect them.
+    pub legacy_assets: bool,
+    pub java_runtime: JavaRuntime,
+    /// Jars of the loader processors that haven't run yet.
+    pub processors: Vec<String>,
+}
+
+impl InstallPlan {
+    /// Returns the files that will be downloaded.
+    pub fn downloads(&self) -> impl Iterator<Item = &DownloadFile> {
+        self.files.iter().filter(|file| file.download)
+    }
+
+    /// Returns the number of bytes that will be downloaded.
+    pub fn download_size(&self) -> u64 {
+        self.downloads().map(|file| file.size).sum()
+    }
+}
+
+/// Installs the version described by the config, downloading only what's missing or corrupt.
+///
+/// This is `plan_install` followed by `execute_plan`.
+pub async fn install(config: &Config, emitter: Option<&Emitter>) -> crate::Result<()> {
+    let plan = plan_install(config, emitter).await?;
+    execute_plan(&plan, config, emitter).await
+}
+
+/// Works out what `install` would do without downloading any game file.
+///
+/// The version meta, asset index and Java file manifest are still fetched and
+/// cached, and loaders based on an installer download it to read their profile.
+/// Every file on disk is verified against its hash.
+pub async fn plan_install(
+    config: &Config,
+    emitter: Option<&Emitter>,
+) -> crate::Result<InstallPlan> {
+    let metadata = load_metadata(config, config.offline, emitter).await?;
+
+    let natives_path = config.get_natives_path().join(&config.version);
+    let check_natives = !natives_path.is_dir() || fs::read_dir(&natives_path)?.count() == 0;
+    let mut plan = build_plan(config, &metadata, check_natives)?;
+
+    emitter.emit(Event::PhaseStarted(Phase::Verification));
+    // Hashing runs on the blocking pool so it doesn't stall the async runtime.
+    let cache_path = config.get_verification_cache_path();
+    let cache = Arc::new(VerificationCache::load(&cache_path, config.force_verification).await);
+    let mut files = std::mem::take(&mut plan.files);
+    let blocking_emitter = emitter.cloned();
+    let blocking_cache = cache.clone();
+    plan.files = tokio::task::spawn_blocking(move || {
+        verify_files(&mut files, &blocking_cache, blocking_emitter.as_ref());
+        files
+    })
+    .await?;
+    cache.save(&cache_path).await?;
+    emitter.emit(Event::PhaseFinished(Phase::Verification));
+
+    Ok(plan)
+}
+
+/// Builds a plan from the metadata without checking the files on disk.
+///
+/// The natives are only extracted when `check_natives` is set.
+pub(crate) fn build_plan(
+    config: &Config,
+    metadata: &Metadata,
+    check_natives: bool,
+) -> crate::Result<InstallPlan> {
+    let Metadata {
+        meta,
+        asset_index,
+        java_files,
+        java_version,
+    } = metadata;
+
+    let mut to_be_extracted = Vec::with_capacity(10);
+    let runtime_path = config.get_runtime_path().join(&java_version.component);
+
+    let files = build_file_map(
+        asset_index,
+        meta,
+        java_files,
+        &runtime_path,
+        config,
+        check_natives,
+        &mut to_be_extracted,
+    )?;
+
+    let processors = meta
+        .processors
+        .iter()
+        .flatten()
+        .filter(|processor| {
+            !processor.success
+                && processor
+                    .sides
+                    .as_ref()
+                    .is_none_or(|sides| sides.iter().any(|side| side == "client"))
+        })
+        .map(|processor| processor.jar.clone())
+        .collect();
+
+    Ok(InstallPlan {
+        version_name: config.get_version_name(),
+        files,
+        natives: to_be_extracted
+            .into_iter()
+            .filter_map(|file| file.path.map(PathBuf::from))
+            .collect(),
+        natives_path: config.get_natives_path().join(&config.version),
+        legacy_assets: asset_index.map_to_resources.unwrap_or_default()
+            || asset_index.r#virtual.unwrap_or_default(),
+        java_runtime: JavaRuntime {
+            component: java_version.component.clone(),
+            path: runtime_path,
+        },
+        processors,
+    })
 }
 
-pub async fn install<T: Loader>(
-    config: &Config<T>,
+/// Downloads the files, extracts the natives and runs the processors of a plan.
+///
+/// # Errors
+///
+/// In offline mode nothing is downloaded and `Error::MissingFiles`
+/// lists the files the plan would have downloaded, if any.
+pub async fn execute_plan(
+    plan: &InstallPlan,
+    config: &Config,
     emitter: Option<&Emitter>,
 ) -> crate::Result<()> {
-    let manifest: VersionManifest = fetch(VERSION_MANIFEST_ENDPOINT).await?;
-    let version_json_path = config.get_version_json_path();
-    let mut meta: VersionMeta = if !version_json_path.exists() {
-        let mut meta = fetch_version_meta(&manifest, config.version).await?;
-        if let Some(loader) = &config.loader {
-            meta = loader.merge(config, meta, emitter).await?;
+    let cancel = config.cancellation_token.as_ref();
+
+    if config.offline && plan.downloads().next().is_some() {
+        return Err(Error::MissingFiles(
+            plan.downloads().map(|file| file.path.clone()).collect(),
+        ));
+    }
+
+    download_necessary(&plan.files, config, plan.legacy_assets, emitter).await?;
+
+    // The downloader already checked the hashes of the downloaded files.
+    let cache_path = config.get_verification_cache_path();
+    let cache = VerificationCache::load(&cache_path, false).await;
+    for file in plan.downloads() {
+        if let Some(checksum) = &file.checksum {
+            cache.insert(&file.path, checksum);
+        }
+    }
+    cache.save(&cache_path).await?;
+
+    if !plan.natives_path.is_dir() {
+        create_dir_all(&plan.natives_path).await?;
+    }
+
+    if !plan.natives.is_empty() {
+        emitter.emit(Event::PhaseStarted(Phase::Natives));
+        for path in &plan.natives {
+            extract_file(path, &plan.natives_path).await?;
+            emitter.emit(Event::Extracted { path: path.clone() });
         }
+        emitter.emit(Event::PhaseFinished(Phase::Natives));
+    }
+
+    if !plan.processors.is_empty() {
+        check_cancelled(cancel)?;
+        let mut meta: VersionMeta = read_json(config.get_version_json_path()).await?;
+        execute_processors_if_exists(&mut meta, config, emitter).await?;
+    }
+
+    Ok(())
+}
+
+/// The metadata an install is built from.
+pub(crate) struct Metadata {
+    pub meta: VersionMeta,
+    pub asset_index: AssetIndex,
+    pub java_files: JavaFileManifest,
+    pub java_version: JavaVersion,
+}
+
+/// Reads the version meta, asset index and Java file manifest, fetching and
+/// caching the ones that aren't on disk yet unless `cached_only` is set.
+///
+/// # Errors
+///
+/// With `cached_only`, `Error::MissingFiles` lists the metadata files that aren't
+/// cached along with every missing file the cached metadata already names. When
+/// the version JSON itself is missing, nothing else is known and only it's listed.
+pub(crate) async fn load_metadata(
+    config: &Config,
+    cached_only: bool,
+    emitter: Option<&Emitter>,
+) -> crate::Result<Metadata> {
+    let cancel = config.cancellation_token.as_ref();
+    let version_json_path = config.get_version_json_path();
+    if cached_only {
+        ensure_cached(&[&version_json_path])?;
+    }
+
+    emitter.emit(Event::PhaseStarted(Phase::Metadata));
+    let meta: VersionMeta = if !version_json_path.exists() {
+        let manifest: VersionManifest =
+            cancellable(cancel, config.fetch_metadata(VERSION_MANIFEST_ENDPOINT)).await??;
+        let meta = cancellable(cancel, fetch_version_meta(config, &manifest)).await??;
+        emitter.emit(Event::PhaseStarted(Phase::Loader));
+        let meta = config.loader.merge(config, meta, emitter).await?;
+        emitter.emit(Event::PhaseFinished(Phase::Loader));
         write_json(version_json_path, &meta).await?;
         meta
     } else {
@@ -73,82 +320,98 @@ pub async fn install<T: Loader>(
     let asset_index_path = config
         .get_indexes_path()
         .join(format!("{}.json", &meta.asset_index.id));
+    let java_version = meta.java_version.clone().unwrap_or_default();
+    let java_manifest_path = config.get_java_manifest_path(&java_version.component);
+    if cached_only {
+        let mut missing = missing_files(&[&asset_index_path, &java_manifest_path]);
+        if !missing.is_empty() {
+            let metadata = Metadata {
+                asset_index: read_cached(&asset_index_path).await?,
+                java_files: read_cached(&java_manifest_path).await?,
+                java_version,
+                meta,
+            };
+            missing.extend(
+                build_plan(config, &metadata, false)?
+                    .files
+                    .into_iter()
+                    .filter(|file| !file.url.is_empty() && !file.path.is_file())
+                    .map(|file| file.path),
+            );
+            return Err(Error::MissingFiles(missing));
+        }
+    }
+
     let asset_index: AssetIndex = if !asset_index_path.exists() {
-        let asset_index = fetch(&meta.asset_index.url).await?;
+        let asset_index =
+            cancellable(cancel, config.mirrors.fetch(&meta.asset_index.url)).await??;
         write_json(asset_index_path, &asset_index).await?;
         asset_index
     } else {
         read_json(asset_index_path).await?
     };
 
-    let version_jar_path = config.get_version_jar_path();
-    if !version_jar_path.exists()
-        || !calculate_sha1(&version_jar_path)?.eq(&meta.downloads.client.sha1)
-    {
-        download(&meta.downloads.client.url, version_jar_path, emitter).await?;
-    }
-
-    let natives_path = config.get_natives_path().join(config.version);
-    if !natives_path.is_dir() {
-        create_dir_all(&natives_path).await?;
-    }
-
-    let check_natives = fs::read_dir(&natives_path)?.count() == 0;
-    let mut to_be_extracted = Vec::with_capacity(10);
-
-    let default_java_version = JavaVersion::default();
-    let java_version = meta.java_version.as_ref().unwrap_or(&default_java_version);
-    let runtime_path = config.get_runtime_path().join(&java_version.component);
-
-    let java_manifest: JavaManifest = fetch(JAVA_MANIFEST_ENDPOINT).await?;
-    let java_url = get_java_url(&java_manifest, java_version)?;
-    let java_files: JavaFileManifest = fetch(java_url).await?;
+    let java_files: JavaFileManifest = if cached_only {
+        read_json(&java_manifest_path).await?
+    } else {
+        let java_manifest: JavaManifest =
+            cancellable(cancel, config.fetch_metadata(JAVA_MANIFEST_ENDPOINT)).await??;
+        let java_url = get_java_url(&java_manifest, &java_version)?;
+        let java_files = cancellable(cancel, config.fetch_metadata(&java_url)).await??;
+        write_json(&java_manifest_path, &java_files).await?;
+        java_files
+    };
+    emitter.emit(Event::PhaseFinished(Phase::Metadata));
 
-    let file_map = build_file_map(
-        &asset_index,
-        &meta,
-        &java_files,
-        &runtime_path,
-        config,
-        check_natives,
-        &mut to_be_extracted,
-    )?;
+    Ok(Metadata {
+        meta,
+        asset_index,
+        java_files,
+        java_version,
+    })
+}
 
-    download_necessary(
-        file_map,
-        &config.game_dir,
-        asset_index.map_to_resources.unwrap_or_default()
-            || asset_index.r#virtual.unwrap_or_default(),
-        emitter,
-    )
-    .await?;
+/// Fails with the given paths that don't exist, used in offline mode
+/// where nothing can be fetched to replace them.
+fn ensure_cached(paths: &[&Path]) -> crate::Result<()> {
+    let missing = missing_files(paths);
 
-    if !to_be_extracted.is_empty() {
-        create_dir_all(&natives_path).await?;
-        for extract in to_be_extracted {
-            let path = PathBuf::from(extract.path.unwrap());
-            download(&extract.url, &path, emitter).await?;
-            extract_file(&path, &natives_path).await?;
-        }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::MissingFiles(missing))
     }
+}
 
-    execute_processors_if_exists(&mut meta, config).await?;
+fn missing_files(paths: &[&Path]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|path| !path.is_file())
+        .map(|path| path.to_path_buf())
+        .collect()
+}
 
-    Ok(())
+/// Reads a cached metadata file, or an empty one when it isn't cached.
+async fn read_cached<T: DeserializeOwned + Default>(path: &Path) -> crate::Result<T> {
+    if path.is_file() {
+        read_json(path).await
+    } else {
+        Ok(T::default())
+    }
 }
 
 async fn fetch_version_meta(
+    config: &Config,
     manifest: &VersionManifest,
-    version: &str,
 ) -> crate::Result<VersionMeta> {
     let version_url = manifest
         .versions
         .iter()
-        .find(|v| v.id == version)
+        .find(|v| v.id == config.version)
         .ok_or_else(|| Error::UnknownVersion("Vanilla".to_string()))?
         .url
         .clone();
-    fetch(&version_url).await
+    config.mirrors.fetch(&version_url).await
 }
 
 fn get_java_url(java_manifest: &JavaManifest, java_version: &JavaVersion) -> crate::Result<String> {
@@ -183,12 +446,12 @@ fn get_java_url(java_manifest: &JavaManifest, java_version: &JavaVersion) -> cra
         .cloned()
 }
 
-fn build_file_map(
+pub(crate) fn build_file_map(
     asset_index: &AssetIndex,
     meta: &VersionMeta,
     java_files: &JavaFileManifest,
     runtime_path: &Path,
-    config: &Config<impl Loader>,
+    config: &Config,
     check_natives: bool,
     to_be_extracted: &mut Vec<vanilla::File>,
 ) -> crate::Result<Vec<DownloadFile>> {
@@ -200,13 +463,15 @@ fn build_file_map(
             let hash = &meta.hash;
             DownloadFile {
                 file_name: key.clone(),
-                sha1: hash.clone(),
+                checksum: Checksum::sha1(hash.clone()),
+                size: meta.size,
                 url: format!("{}/{}/{}", RESOURCES_ENDPOINT, &hash[0..2], hash),
                 path: assets_path.join("objects").join(&hash[0..2]).join(hash),
                 r#type: FileType::Asset {
                     is_map: asset_index.map_to_resources.unwrap_or_default(),
                     is_virtual: asset_index.r#virtual.unwrap_or_default(),
                 },
+                download: false,
             }
         })
         .collect::<Vec<_>>();
@@ -230,16 +495,12 @@ fn build_file_map(
                     if let Some(classifier) = classifier {
                         if let Some(classifier_path) = &classifier.path {
                             let path = config
-                                .game_dir
-                                .join("libraries")
+                                .get_libraries_path()
                                 .join(classifier_path.replace("/", MAIN_SEPARATOR_STR));
                             let url = classifier.url.clone();
-                            let sha1 = classifier.sha1.clone();
                             to_be_extracted.push(vanilla::File {
                                 path: Some(path.to_string_lossy().into_owned()),
-                                sha1: sha1.clone(),
-                                size: classifier.size,
-                                url: url.clone(),
+                                ..classifier.clone()
                             });
                             return Some(DownloadFile {
                                 file_name: PathBuf::from(url.clone())
@@ -247,10 +508,12 @@ fn build_file_map(
                                     .unwrap_or_default()
                                     .to_string_lossy()
                                     .to_string(),
-                                sha1,
+                                checksum: classifier.checksum(),
+                                size: classifier.size as u64,
                                 url,
                                 path,
-                                r#type: FileType::Library,
+                                r#type: FileType::Native,
+                                download: false,
                             });
                         }
                     }
@@ -263,13 +526,14 @@ fn build_file_map(
                     .unwrap_or_default()
                     .to_string_lossy()
                     .to_string(),
-                sha1: artifact.sha1.clone(),
+                checksum: artifact.checksum(),
+                size: artifact.size as u64,
                 url: artifact.url.clone(),
                 path: config
-                    .game_dir
-                    .join("libraries")
+                    .get_libraries_path()
                     .join(artifact.path.as_ref()?.replace("/", MAIN_SEPARATOR_STR)),
                 r#type: FileType::Library,
+                download: false,
             })
         })
         .collect::<Vec<_>>();
@@ -286,21 +550,35 @@ fn build_file_map(
                     .unwrap_or(name)
                     .to_string(),
                 path,
-                sha1: downloads.raw.sha1.clone(),
+                checksum: Checksum::sha1(downloads.raw.sha1.clone()),
+                size: downloads.raw.size,
                 url: downloads.raw.url.clone(),
                 r#type: FileType::Java,
+                download: false,
             })
         })
         .collect::<Vec<_>>();
 
-    Ok([asset_files, library_files, java_files].concat())
+    let client_file = DownloadFile {
+        file_name: format!("{}.jar", config.get_version_name()),
+        checksum: meta.downloads.client.checksum(),
+        size: meta.downloads.client.size as u64,
+        url: meta.downloads.client.url.clone(),
+        path: config.get_version_jar_path(),
+        r#type: FileType::Client,
+        download: false,
+    };
+
+    Ok([vec![client_file], asset_files, library_files, java_files].concat())
 }
 
 async fn execute_processors_if_exists(
     meta: &mut VersionMeta,
-    config: &Config<impl Loader>,
+    config: &Config,
+    emitter: Option<&Emitter>,
 ) -> crate::Result<()> {
     if let Some(ref mut processors) = meta.processors {
+        emitter.emit(Event::PhaseStarted(Phase::Processors));
         let data = meta
             .data
             .as_ref()
@@ -308,7 +586,8 @@ async fn execute_processors_if_exists(
 
         let libraries_path = config.get_libraries_path();
 
-        for processor in processors {
+        let total = processors.len();
+        for (i, processor) in processors.iter_mut().enumerate() {
             if let Some(sides) = &processor.sides {
                 if !sides.contains(&"client".to_string()) {
                     continue;
@@ -319,6 +598,11 @@ async fn execute_processors_if_exists(
                 continue;
             }
 
+            emitter.emit(Event::Processor {
+                current: i + 1,
+                total,
+            });
+
             let classpath = processor
                 .classpath
                 .iter()
@@ -333,8 +617,6 @@ async fn execute_processors_if_exists(
                 .collect::<Vec<String>>()
                 .join(CLASSPATH_SEPARATOR);
 
-            println!("{}", classpath);
-
             let main_class = read_file_from_jar(
                 &libraries_path
                     .join(parse_lib_path(&processor.jar)?)
@@ -352,8 +634,6 @@ async fn execute_processors_if_exists(
             .trim()
             .to_string();
 
-            println!("{}", main_class);
-
             let args = processor
                 .args
                 .iter()
@@ -386,9 +666,7 @@ async fn execute_processors_if_exists(
                 })
                 .collect::<Vec<_>>();
 
-            println!("{:?}", parse_lib_path(&processor.jar)?);
-
-            let child = Command::new(
+            let mut command = Command::new(
                 config
                     .get_java_path(
                         meta.java_version
@@ -396,21 +674,24 @@ async fn execute_processors_if_exists(
                             .unwrap_or(&JavaVersion::default()),
                     )
                     .await?,
-            )
-            .arg("-cp")
-            .arg(format!(
-                "{}{}{}",
-                classpath,
-                CLASSPATH_SEPARATOR,
-                libraries_path
-                    .join(parse_lib_path(&processor.jar)?)
-                    .to_string_lossy()
-                    .into_owned()
-            ))
-            .arg(main_class)
-            .args(args)
-            .output()
-            .await?;
+            );
+            // The processor is killed when the output future is dropped on cancellation.
+            command
+                .arg("-cp")
+                .arg(format!(
+                    "{}{}{}",
+                    classpath,
+                    CLASSPATH_SEPARATOR,
+                    libraries_path
+                        .join(parse_lib_path(&processor.jar)?)
+                        .to_string_lossy()
+                        .into_owned()
+                ))
+                .arg(main_class)
+                .args(args)
+                .kill_on_drop(true);
+
+            let child = cancellable(config.cancellation_token.as_ref(), command.output()).await??;
 
             if child.status.success() {
                 processor.success = true;
@@ -421,6 +702,7 @@ async fn execute_processors_if_exists(
                 )));
             }
         }
+        emitter.emit(Event::PhaseFinished(Phase::Processors));
     }
 
     write_json(config.get_version_json_path(), &meta).await?;
@@ -428,60 +710,406 @@ async fn execute_processors_if_exists(
     Ok(())
 }
 
+/// Returns whether a downloadable file is missing or doesn't match its hash.
+fn is_broken(file: &DownloadFile, cache: &VerificationCache) -> bool {
+    if file.url.is_empty() {
+        return false;
+    }
+    !file.path.exists()
+        || file.checksum.as_ref().is_some_and(|checksum| {
+            cache
+                .matches(&file.path, checksum)
+                .is_ok_and(|matches| !matches)
+        })
+}
+
+/// Marks the files that are missing or corrupt for download, reporting every checked file.
+///
+/// Files the cache already verified are skipped unless it's forced.
+fn verify_files(files: &mut [DownloadFile], cache: &VerificationCache, emitter: Option<&Emitter>) {
+    let checked = AtomicUsize::new(0);
+    let total = files.len();
+    files.par_iter_mut().for_each(|file| {
+        file.download = is_broken(file, cache);
+        emitter.emit(Event::FileVerified {
+            path: file.path.clone(),
+            checked: checked.fetch_add(1, Ordering::Relaxed) + 1,
+            total,
+        });
+    });
+}
+
+/// Returns the number and size of the files to download per kind.
+fn download_plan(files: &[DownloadFile]) -> Vec<DownloadTotal> {
+    let mut plan: Vec<DownloadTotal> = Vec::new();
+    for file in files.iter().filter(|file| file.download) {
+        let kind = file.r#type.kind();
+        match plan.iter_mut().find(|total| total.kind == kind) {
+            Some(total) => {
+                total.files += 1;
+                total.bytes += file.size;
+            }
+            None => plan.push(DownloadTotal {
+                kind,
+                files: 1,
+                bytes: file.size,
+            }),
+        }
+    }
+    plan
+}
+
+/// Downloads the broken files, the client jar and Java runtime first, and
+/// copies the assets of legacy versions to where those versions expect them.
 async fn download_necessary(
-    files: Vec<DownloadFile>,
-    game_dir: &Path,
+    files: &[DownloadFile],
+    config: &Config,
     legacy: bool,
     emitter: Option<&Emitter>,
 ) -> crate::Result<()> {
-    let broken_ones: Vec<(String, PathBuf)> = files
-        .par_iter()
-        .filter_map(|file| {
-            if file.url.is_empty() {
-                return None;
-            }
-            if !file.path.exists()
-                || (!file.sha1.is_empty() && calculate_sha1(&file.path).ok()? != file.sha1)
-            {
-                return Some((file.url.clone(), file.path.clone()));
-            }
-            None
-        })
-        .collect();
+    emitter.emit(Event::DownloadPlan(download_plan(files)));
+    let progress = ProgressTracker::new(
+        files
+            .iter()
+            .filter(|file| file.download)
+            .map(|file| file.size)
+            .sum(),
+    );
 
-    download_multiple(broken_ones, emitter).await?;
+    for phase in [Phase::Client, Phase::JavaRuntime, Phase::Download] {
+        let files: Vec<&DownloadFile> = files
+            .iter()
+            .filter(|file| file.download && file.r#type.phase() == phase)
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+
+        let tasks: Vec<DownloadTask> = files
+            .iter()
+            .map(|file| {
+                config.mirrors.task(
+                    &file.url,
+                    file.path.clone(),
+                    Some(Integrity::with_checksum(file.checksum.clone(), file.size)),
+                )
+            })
+            .collect();
+
+        emitter.emit(Event::PhaseStarted(phase));
+        progress.start_phase(phase, files.iter().map(|file| file.size).sum());
+        download_multiple(
+            tasks,
+            &config.download_policy,
+            emitter,
+            config.cancellation_token.as_ref(),
+            Some(&progress),
+        )
+        .await?;
+        progress.emit(emitter, true);
+        emitter.emit(Event::PhaseFinished(phase));
+    }
 
     if legacy {
-        files.par_iter().try_for_each(|file| {
-            if let FileType::Asset { is_virtual, is_map } = file.r#type {
+        let copies: Vec<(PathBuf, PathBuf, Option<Checksum>)> = files
+            .iter()
+            .filter_map(|file| {
+                let FileType::Asset { is_virtual, is_map } = file.r#type else {
+                    return None;
+                };
                 let target_path = if is_virtual {
-                    game_dir
-                        .join("assets")
+                    config
+                        .get_assets_path()
                         .join("virtual")
                         .join("legacy")
                         .join(&file.file_name)
                 } else if is_map {
-                    game_dir.join("resources").join(&file.file_name)
+                    config.game_dir.join("resources").join(&file.file_name)
                 } else {
-                    return None::<()>;
+                    return None;
                 };
+                Some((file.path.clone(), target_path, file.checksum.clone()))
+            })
+            .collect();
 
-                if let Some(parent) = target_path.parent() {
-                    if !parent.is_dir() {
-                        fs::create_dir_all(parent).ok();
+        tokio::task::spawn_blocking(move || {
+            copies
+                .par_iter()
+                .for_each(|(source, target_path, checksum)| {
+                    if let Some(parent) = target_path.parent() {
+                        if !parent.is_dir() {
+                            fs::create_dir_all(parent).ok();
+                        }
                     }
+
+                    if !target_path.exists()
+                        || checksum
+                            .as_ref()
+                            .is_some_and(|checksum| !checksum.matches(target_path).unwrap_or(true))
+                    {
+                        fs::copy(source, target_path).ok();
+                    }
+                });
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use tokio_util::sync::CancellationToken;
+
+    use super::{execute_plan, plan_install, DownloadFile, FileType, InstallPlan, JavaRuntime};
+    use crate::{
+        auth::AuthMethod,
+        error::Error,
+        http::{
+            downloader::{
+                part_path,
+                tests::{serve, BODY_SHA1},
+            },
+            mirror::Mirrors,
+        },
+        minecraft::{
+            config::{Config, ConfigBuilder},
+            RESOURCES_ENDPOINT,
+        },
+        util::hash::Checksum,
+    };
+
+    /// Path of the library of `version_meta` inside the libraries directory.
+    pub(crate) const LIBRARY_PATH: &str = "org/lyceris/lib/1.0/lib-1.0.jar";
+    /// Name of the file of the Java runtime written by `write_metadata`.
+    pub(crate) const JAVA_FILE: &str = "bin/java";
+
+    /// Returns a config in a new temporary directory that downloads the assets from
+    /// the server of `url`, see `downloader::tests::serve`.
+    pub(crate) fn test_config(url: &str) -> ConfigBuilder {
+        let dir = std::env::temp_dir().join(format!("lyceris-install-{}", uuid::Uuid::new_v4()));
+        let origin = url.rsplit_once('/').map_or(url, |(origin, _)| origin);
+        ConfigBuilder::new(
+            dir,
+            "1.21.4",
+            AuthMethod::Offline {
+                username: "lyceris".into(),
+                uuid: None,
+            },
+        )
+        .mirrors(Mirrors::new().mirror(RESOURCES_ENDPOINT, [origin]))
+    }
+
+    /// A version with a client jar and a library, every file being the body of `serve`.
+    pub(crate) fn version_meta(url: &str) -> serde_json::Value {
+        let file = |path: &str| serde_json::json!({ "sha1": BODY_SHA1, "size": 7, "url": url, "path": path });
+        serde_json::json!({
+            "assetIndex": { "id": "19", "sha1": BODY_SHA1, "size": 7, "url": url },
+            "assets": "19",
+            "downloads": { "client": file("client.jar"), "server": file("server.jar") },
+            "id": "1.21.4",
+            "javaVersion": { "component": "java-runtime-delta", "majorVersion": 21 },
+            "libraries": [{ "name": "org.lyceris:lib:1.0", "downloads": { "artifact": file(LIBRARY_PATH) } }],
+            "mainClass": "net.minecraft.client.main.Main",
+            "releaseTime": "2024-12-03T10:12:57+00:00",
+            "time": "2024-12-03T10:12:57+00:00",
+            "type": "release"
+        })
+    }
+
+    /// Caches the version JSON, an asset index with one asset and
+    /// a Java runtime with one file, as an install would.
+    pub(crate) fn write_metadata(config: &Config, meta: &serde_json::Value, url: &str) {
+        let asset_index =
+            serde_json::json!({ "objects": { "icon.png": { "hash": BODY_SHA1, "size": 7 } } });
+        let java_files = serde_json::json!({
+            "files": {
+                "bin": { "type": "directory" },
+                JAVA_FILE: {
+                    "type": "file",
+                    "downloads": { "raw": { "sha1": BODY_SHA1, "size": 7, "url": url } }
                 }
+            }
+        });
+
+        for (path, json) in [
+            (config.get_version_json_path(), meta),
+            (config.get_indexes_path().join("19.json"), &asset_index),
+            (
+                config.get_java_manifest_path("java-runtime-delta"),
+                &java_files,
+            ),
+        ] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, json.to_string()).unwrap();
+        }
+    }
+
+    /// Returns the paths of the client jar, the asset, the library and the Java file.
+    pub(crate) fn game_files(config: &Config) -> [PathBuf; 4] {
+        [
+            config.get_version_jar_path(),
+            config
+                .get_assets_path()
+                .join("objects")
+                .join(&BODY_SHA1[..2])
+                .join(BODY_SHA1),
+            config.get_libraries_path().join(LIBRARY_PATH),
+            config
+                .get_runtime_path()
+                .join("java-runtime-delta")
+                .join(JAVA_FILE),
+        ]
+    }
+
+    #[tokio::test]
+    async fn cancelling_an_install_leaves_no_partial_files() {
+        let url = serve(true).await;
+        let dir = std::env::temp_dir().join(format!("lyceris-install-{}", uuid::Uuid::new_v4()));
+        let cancel = CancellationToken::new();
+        let config = ConfigBuilder::new(
+            &dir,
+            "1.21.4",
+            AuthMethod::Offline {
+                username: "lyceris".into(),
+                uuid: None,
+            },
+        )
+        .cancellation_token(cancel.clone())
+        .build();
+
+        let files: Vec<DownloadFile> = ["a.jar", "b.jar"]
+            .into_iter()
+            .map(|name| DownloadFile {
+                file_name: name.to_string(),
+                checksum: Checksum::sha1(BODY_SHA1),
+                size: 7,
+                url: url.clone(),
+                path: config.get_libraries_path().join(name),
+                r#type: FileType::Library,
+                download: true,
+            })
+            .collect();
+        let plan = InstallPlan {
+            version_name: config.get_version_name(),
+            natives: Vec::new(),
+            natives_path: config.get_natives_path().join(&config.version),
+            legacy_assets: false,
+            java_runtime: JavaRuntime {
+                component: "java-runtime-delta".to_string(),
+                path: config.get_runtime_path().join("java-runtime-delta"),
+            },
+            processors: Vec::new(),
+            files,
+        };
 
-                if !target_path.exists() || calculate_sha1(&target_path).ok()? != file.sha1 {
-                    fs::copy(&file.path, &target_path).ok();
+        let parts: Vec<_> = plan
+            .files
+            .iter()
+            .map(|file| part_path(&file.path))
+            .collect();
+        let watcher = {
+            let parts = parts.clone();
+            tokio::spawn(async move {
+                while !parts.iter().all(|part| part.is_file()) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
                 }
+                cancel.cancel();
+            })
+        };
 
-                return None;
+        let result = execute_plan(&plan, &config, None).await;
+        watcher.await.unwrap();
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        for (file, part) in plan.files.iter().zip(&parts) {
+            assert!(!file.path.exists());
+            assert!(!part.exists());
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn plans_only_the_missing_files() {
+        let url = "https://example.com/file";
+        let config = test_config(url).offline(true).build();
+        let native_path = "org/lyceris/natives/1.0/natives-1.0.jar";
+        let native =
+            serde_json::json!({ "sha1": BODY_SHA1, "size": 7, "url": url, "path": native_path });
+        let processor = |jar: &str, sides: serde_json::Value, success: bool| serde_json::json!({ "classpath": [], "args": [], "sides": sides, "jar": jar, "success": success });
+        let mut meta = version_meta(url);
+        meta["libraries"].as_array_mut().unwrap().push(serde_json::json!({
+            "name": "org.lyceris:natives:1.0",
+            "downloads": {
+                "classifiers": { "natives-linux": native, "natives-macos": native, "natives-windows": native }
             }
+        }));
+        meta["processors"] = serde_json::json!([
+            processor(
+                "org.lyceris:client:1.0",
+                serde_json::json!(["client"]),
+                false
+            ),
+            processor(
+                "org.lyceris:server:1.0",
+                serde_json::json!(["server"]),
+                false
+            ),
+            processor("org.lyceris:done:1.0", serde_json::Value::Null, true),
+        ]);
+        write_metadata(&config, &meta, url);
 
-            None
-        });
+        let [client, asset, library, java] = game_files(&config);
+        for path in [&asset, &library] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"lyceris").unwrap();
+        }
+
+        let plan = plan_install(&config, None).await.unwrap();
+
+        let native = config.get_libraries_path().join(native_path);
+        let mut downloads: Vec<_> = plan.downloads().map(|file| &file.path).collect();
+        downloads.sort();
+        let mut expected = vec![&client, &java, &native];
+        expected.sort();
+        assert_eq!(downloads, expected);
+        assert_eq!(plan.files.len(), 5);
+        assert_eq!(plan.download_size(), 21);
+        assert_eq!(plan.natives, vec![native]);
+        assert_eq!(plan.processors, vec!["org.lyceris:client:1.0"]);
+
+        let json = serde_json::to_value(&plan).unwrap();
+        let round_trip: InstallPlan = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&round_trip).unwrap(), json);
+
+        std::fs::remove_dir_all(&config.game_dir).unwrap();
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn offline_installs_report_every_missing_file() {
+        let config = test_config("https://example.com/file")
+            .offline(true)
+            .build();
+        let meta = version_meta("https://example.com/file");
+        let version_json_path = config.get_version_json_path();
+        std::fs::create_dir_all(version_json_path.parent().unwrap()).unwrap();
+        std::fs::write(&version_json_path, meta.to_string()).unwrap();
+
+        let Err(Error::MissingFiles(missing)) = plan_install(&config, None).await else {
+            panic!("expected the missing files");
+        };
+        for path in [
+            config.get_indexes_path().join("19.json"),
+            config.get_java_manifest_path("java-runtime-delta"),
+            config.get_version_jar_path(),
+            config.get_libraries_path().join(LIBRARY_PATH),
+        ] {
+            assert!(missing.contains(&path), "{} isn't listed", path.display());
+        }
+
+        std::fs::remove_dir_all(&config.game_dir).unwrap();
+    }
 }
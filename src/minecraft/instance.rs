@@ -0,0 +1,317 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    auth::AuthMethod,
+    error::Error,
+    util::json::{read_json, write_json},
+};
+
+use super::{
+    config::{Config, Memory},
+    loader::LoaderKind,
+};
+
+const INSTANCES_DIR: &str = "instances";
+const DESCRIPTOR_FILE: &str = "instance.json";
+
+/// Descriptor of a game instance, persisted as `instance.json`
+/// inside the instance's game directory.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Instance {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub loader: LoaderKind,
+    pub memory: Option<Memory>,
+    pub version_name: Option<String>,
+    pub java_version: Option<String>,
+    #[serde(default)]
+    pub custom_java_args: Vec<String>,
+    #[serde(default)]
+    pub custom_args: Vec<String>,
+}
+
+impl Instance {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            loader: LoaderKind::Vanilla,
+            memory: None,
+            version_name: None,
+            java_version: None,
+            custom_java_args: Vec::new(),
+            custom_args: Vec::new(),
+        }
+    }
+
+    pub fn loader(mut self, loader: impl Into<LoaderKind>) -> Self {
+        self.loader = loader.into();
+        self
+    }
+
+    pub fn memory(mut self, memory: Memory) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+}
+
+/// Manages named instances under a common root.
+///
+/// Every instance gets its own game directory at `instances/<name>`
+/// for saves, mods and options, while `libraries`, `assets`, `natives`,
+/// `runtimes` and `versions` are stored once in the root and shared.
+#[derive(Clone, Debug)]
+pub struct InstanceManager {
+    root: PathBuf,
+}
+
+impl InstanceManager {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn get_root_path(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn get_instances_path(&self) -> PathBuf {
+        self.root.join(INSTANCES_DIR)
+    }
+
+    pub fn get_instance_path(&self, name: &str) -> PathBuf {
+        self.get_instances_path().join(name)
+    }
+
+    /// Creates the instance directory and writes its descriptor.
+    pub async fn create(&self, instance: Instance) -> crate::Result<Instance> {
+        validate_name(&instance.name)?;
+        let path = self.get_instance_path(&instance.name);
+        if path.exists() {
+            return Err(Error::AlreadyExists(format!(
+                "Instance '{}'",
+                instance.name
+            )));
+        }
+
+        fs::create_dir_all(&path).await?;
+        self.save(&instance).await?;
+
+        Ok(instance)
+    }
+
+    /// Persists the descriptor of an existing instance.
+    pub async fn save(&self, instance: &Instance) -> crate::Result<()> {
+        validate_name(&instance.name)?;
+        write_json(
+            self.get_instance_path(&instance.name).join(DESCRIPTOR_FILE),
+            instance,
+        )
+        .await
+    }
+
+    pub async fn get(&self, name: &str) -> crate::Result<Instance> {
+        validate_name(name)?;
+        let descriptor = self.get_instance_path(name).join(DESCRIPTOR_FILE);
+        if !descriptor.is_file() {
+            return Err(Error::NotFound(format!("Instance '{}'", name)));
+        }
+        read_json(descriptor).await
+    }
+
+    /// Lists every instance with a readable descriptor, sorted by name.
+    pub async fn list(&self) -> crate::Result<Vec<Instance>> {
+        let instances_path = self.get_instances_path();
+        if !instances_path.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut instances = Vec::new();
+        let mut entries = fs::read_dir(&instances_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let descriptor = entry.path().join(DESCRIPTOR_FILE);
+            if descriptor.is_file() {
+                if let Ok(instance) = read_json::<Instance, _>(descriptor).await {
+                    instances.push(instance);
+                }
+            }
+        }
+        instances.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(instances)
+    }
+
+    pub async fn rename(&self, name: &str, new_name: &str) -> crate::Result<Instance> {
+        let mut instance = self.get(name).await?;
+        validate_name(new_name)?;
+        let new_path = self.get_instance_path(new_name);
+        if new_path.exists() {
+            return Err(Error::AlreadyExists(format!("Instance '{}'", new_name)));
+        }
+
+        fs::rename(self.get_instance_path(name), &new_path).await?;
+        instance.name = new_name.to_string();
+        self.save(&instance).await?;
+
+        Ok(instance)
+    }
+
+    /// Removes the instance's game directory. Shared files are kept.
+    pub async fn delete(&self, name: &str) -> crate::Result<()> {
+        self.get(name).await?;
+        fs::remove_dir_all(self.get_instance_path(name)).await?;
+        Ok(())
+    }
+
+    /// Copies the instance's game directory, including saves and mods, under a new name.
+    pub async fn duplicate(&self, name: &str, new_name: &str) -> crate::Result<Instance> {
+        let mut instance = self.get(name).await?;
+        validate_name(new_name)?;
+        let new_path = self.get_instance_path(new_name);
+        if new_path.exists() {
+            return Err(Error::AlreadyExists(format!("Instance '{}'", new_name)));
+        }
+
+        copy_dir(&self.get_instance_path(name), &new_path).await?;
+        instance.name = new_name.to_string();
+        self.save(&instance).await?;
+
+        Ok(instance)
+    }
+
+    /// Builds a launch configuration for the instance using the shared root.
+    pub fn config(&self, instance: &Instance, authentication: AuthMethod) -> Config {
+        Config {
+            game_dir: self.get_instance_path(&instance.name),
+            version: instance.version.clone(),
+            authentication,
+            memory: instance.memory.clone(),
+            version_name: instance.version_name.clone(),
+            loader: instance.loader.clone(),
+            java_version: instance.java_version.clone(),
+            runtime_dir: None,
+            shared_dir: Some(self.root.clone()),
+            custom_java_args: instance.custom_java_args.clone(),
+            custom_args: instance.custom_args.clone(),
+            download_policy: Default::default(),
+            mirrors: Default::default(),
+            metadata_cache: Default::default(),
+            offline: false,
+            force_verification: false,
+            cancellation_token: None,
+        }
+    }
+}
+
+/// Device names Windows reserves regardless of the extension, e.g. `nul.txt`.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Rejects names that aren't a valid directory name on every platform.
+fn validate_name(name: &str) -> crate::Result<()> {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+        || name.chars().any(char::is_control)
+        || name.ends_with(['.', ' '])
+        || RESERVED_NAMES
+            .iter()
+            .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        return Err(Error::Parse(format!("Invalid instance name: {}", name)));
+    }
+    Ok(())
+}
+
+async fn copy_dir(from: &Path, to: &Path) -> crate::Result<()> {
+    let mut stack = vec![(from.to_path_buf(), to.to_path_buf())];
+
+    while let Some((from, to)) = stack.pop() {
+        fs::create_dir_all(&to).await?;
+        let mut entries = fs::read_dir(&from).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let target = to.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                stack.push((entry.path(), target));
+            } else {
+                fs::copy(entry.path(), target).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_name, Instance, InstanceManager};
+
+    #[test]
+    fn rejects_names_that_are_invalid_on_any_platform() {
+        for name in [
+            "",
+            ".",
+            "..",
+            "a/b",
+            "a\\b",
+            "a:b",
+            "a*",
+            "a?",
+            "a\"b",
+            "a<b",
+            "a>b",
+            "a|b",
+            "a\tb",
+            "trailing.",
+            "trailing ",
+            "CON",
+            "nul",
+            "Com1",
+            "lpt9.txt",
+        ] {
+            assert!(validate_name(name).is_err(), "{:?} was accepted", name);
+        }
+        for name in ["survival", "1.21.4 modded", "console", "com10", ".hidden"] {
+            assert!(validate_name(name).is_ok(), "{:?} was rejected", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn manages_instances() {
+        let root = std::env::temp_dir().join(format!("lyceris-instances-{}", uuid::Uuid::new_v4()));
+        let manager = InstanceManager::new(&root);
+
+        manager
+            .create(Instance::new("survival", "1.21.4"))
+            .await
+            .unwrap();
+        assert!(manager
+            .create(Instance::new("survival", "1.21.4"))
+            .await
+            .is_err());
+
+        manager.duplicate("survival", "creative").await.unwrap();
+        manager.rename("survival", "hardcore").await.unwrap();
+        manager.delete("creative").await.unwrap();
+
+        let names: Vec<_> = manager
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, vec!["hardcore"]);
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
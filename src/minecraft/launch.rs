@@ -1,4 +1,7 @@
-use std::{collections::HashMap, process::Stdio};
+use std::{
+    collections::HashMap,
+    process::{ExitStatus, Stdio},
+};
 
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
@@ -11,16 +14,13 @@ use crate::{
     error::Error,
     json::version::meta::vanilla::{Arguments, Element, Value, VersionMeta},
     minecraft::{config::Memory, parse::ParseRule},
-    util::json::read_json,
+    util::{cancel::check_cancelled, json::read_json},
 };
 
+use super::emitter::{Emit, Emitter, Event};
 use super::{config::Config, CLASSPATH_SEPARATOR};
-use super::{emitter::Emitter, loader::Loader};
 
-pub async fn launch<T: Loader>(
-    config: &Config<T>,
-    emitter: Option<&Emitter>,
-) -> crate::Result<Child> {
+pub async fn launch(config: &Config, emitter: Option<&Emitter>) -> crate::Result<Child> {
     let version_name = config.get_version_name();
     let mut arguments = Vec::<String>::with_capacity(100);
     let meta: VersionMeta = read_json(&config.get_version_json_path()).await?;
@@ -101,7 +101,7 @@ pub async fn launch<T: Loader>(
         "${natives_directory}",
         config
             .get_natives_path()
-            .join(config.version)
+            .join(&config.version)
             .to_string_lossy()
             .into_owned(),
     );
@@ -180,6 +180,8 @@ pub async fn launch<T: Loader>(
         .get_java_path(&meta.java_version.unwrap_or_default())
         .await?;
 
+    check_cancelled(config.cancellation_token.as_ref())?;
+
     let mut child = Command::new(java_path)
         .args(arguments)
         .stdout(Stdio::piped())
@@ -196,10 +198,22 @@ pub async fn launch<T: Loader>(
         tokio::spawn(async move {
             let mut reader = BufReader::new(stdout).lines();
             while let Some(line) = reader.next_line().await.unwrap() {
-                emitter.emit("console", line).await;
+                emitter.emit(Event::GameLog(line));
             }
         });
     }
 
     Ok(child)
 }
+
+/// Waits for a game started with `launch` to exit and emits `Event::GameExited`.
+pub async fn wait_for_exit(
+    child: &mut Child,
+    emitter: Option<&Emitter>,
+) -> crate::Result<ExitStatus> {
+    let status = child.wait().await?;
+    emitter.emit(Event::GameExited {
+        code: status.code(),
+    });
+    Ok(status)
+}
@@ -3,32 +3,35 @@ use std::{
     env::temp_dir,
 };
 
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    http::downloader::download,
+    http::cache::MetadataSource,
     json::version::meta::{
         custom::{CustomMeta, Library},
         vanilla::{self, VersionMeta},
     },
-    minecraft::{config::Config, emitter::Emitter, parse::parse_lib_path, TARGET_OS},
+    minecraft::{config::Config, emitter::Emitter, parse::parse_lib_path},
     util::{
         extract::{extract_specific_directory, extract_specific_file},
         json::read_json,
     },
 };
 
-use super::Loader;
+use super::{is_stable, parse_maven_versions, Loader, LoaderVersion};
 
 const INSTALLER_JAR_ENDPOINT: &str = "https://maven.minecraftforge.net/net/minecraftforge/forge/{loader_version}/forge-{loader_version}-installer.jar";
 
+const MAVEN_METADATA_ENDPOINT: &str =
+    "https://maven.minecraftforge.net/net/minecraftforge/forge/maven-metadata.xml";
+
+const PROMOTIONS_ENDPOINT: &str =
+    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+
 #[derive(Serialize, Deserialize)]
-struct Mirror {
-    name: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    image: Option<String>,
-    homepage: String,
-    url: String,
+struct Promotions {
+    promos: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -59,116 +62,179 @@ pub struct Data {
     pub server: String,
 }
 
-pub struct Forge(pub &'static str);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Forge(pub String);
+
+impl Forge {
+    /// Lists the Forge versions available for the given game version using
+    /// the Forge maven metadata, flagged with the promoted recommended and latest builds.
+    pub async fn list_versions(
+        source: &MetadataSource,
+        game_version: &str,
+    ) -> crate::Result<Vec<LoaderVersion>> {
+        let metadata = source.fetch_text(MAVEN_METADATA_ENDPOINT).await?;
+        let promotions: Promotions = source.fetch(PROMOTIONS_ENDPOINT).await?;
+
+        let recommended = promotions
+            .promos
+            .get(&format!("{}-recommended", game_version));
+        let latest = promotions.promos.get(&format!("{}-latest", game_version));
+        let prefix = format!("{}-", game_version);
+
+        Ok(parse_maven_versions(&metadata)?
+            .into_iter()
+            .filter_map(|version| {
+                // Old versions carry the game version as a suffix too, e.g. `1.7.10-10.13.4.1614-1.7.10`.
+                let version = version.strip_prefix(&prefix)?;
+                let version = version
+                    .strip_suffix(&format!("-{}", game_version))
+                    .unwrap_or(version)
+                    .to_string();
+                Some(LoaderVersion {
+                    stable: is_stable(&version),
+                    recommended: recommended == Some(&version),
+                    latest: latest == Some(&version),
+                    version,
+                })
+            })
+            .collect())
+    }
+}
 
 impl Loader for Forge {
-    async fn merge<T: Loader>(
-        &self,
-        config: &Config<T>,
-        mut meta: VersionMeta,
-        emitter: Option<&Emitter>,
-    ) -> crate::Result<VersionMeta> {
-        let version_name = config.get_version_name();
-        let profiles_path = config
-            .game_dir
-            .join(".forge")
-            .join("profiles")
-            .join(&version_name);
-
-        let installer_json_path = profiles_path.join(format!("installer-{}.json", &version_name));
-        let version_json_path = profiles_path.join(format!("version-{}.json", &version_name));
-        let installer_path = temp_dir().join(format!("forge-{}.jar", version_name));
-
-        let mut installer: Installer = if installer_json_path.is_file() {
-            read_json(&installer_json_path).await?
-        } else {
-            download_installer(&installer_path, &version_name, emitter).await?;
-            extract_specific_file(
-                &installer_path,
-                "install_profile.json",
-                &installer_json_path,
-            )
-            .await?;
-            read_json(&installer_json_path).await?
-        };
-
-        let version: CustomMeta = if version_json_path.is_file() {
-            read_json(&version_json_path).await?
-        } else {
-            download_installer(&installer_path, &version_name, emitter).await?;
-            extract_specific_file(&installer_path, "version.json", &version_json_path).await?;
-            read_json(&version_json_path).await?
-        };
-
-        process_data(config, &installer_path, &mut installer.data).await?;
-        
-        meta.data = Some(merge_data(
-            config,
-            &meta,
-            installer.data.unwrap_or_default(),
-        ));
-
-        meta.processors = installer.processors;
-
-        extract_specific_directory(
+    fn merge<'a>(
+        &'a self,
+        config: &'a Config,
+        meta: VersionMeta,
+        emitter: Option<&'a Emitter>,
+    ) -> BoxFuture<'a, crate::Result<VersionMeta>> {
+        Box::pin(async move {
+            let installer_url = INSTALLER_JAR_ENDPOINT
+                .replace("{loader_version}", &format!("{}-{}", meta.id, self.0));
+
+            merge_installer(config, meta, "forge", &installer_url, emitter).await
+        })
+    }
+
+    fn get_version(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Merges an installer based loader (Forge and NeoForge) into the vanilla meta.
+///
+/// The installer jar is downloaded from `installer_url` only when the extracted
+/// `install_profile.json` and `version.json` are not cached under
+/// `.{loader_name}/profiles` yet.
+pub(crate) async fn merge_installer(
+    config: &Config,
+    mut meta: VersionMeta,
+    loader_name: &str,
+    installer_url: &str,
+    emitter: Option<&Emitter>,
+) -> crate::Result<VersionMeta> {
+    let version_name = config.get_version_name();
+    let profiles_path = config
+        .get_shared_path()
+        .join(format!(".{}", loader_name))
+        .join("profiles")
+        .join(&version_name);
+
+    let installer_json_path = profiles_path.join(format!("installer-{}.json", &version_name));
+    let version_json_path = profiles_path.join(format!("version-{}.json", &version_name));
+    let installer_path = temp_dir().join(format!("{}-{}.jar", loader_name, version_name));
+
+    let mut installer: Installer = if installer_json_path.is_file() {
+        read_json(&installer_json_path).await?
+    } else {
+        download_installer(config, &installer_path, installer_url, emitter).await?;
+        extract_specific_file(
             &installer_path,
-            "maven/",
-            &config.game_dir.join("libraries"),
+            "install_profile.json",
+            &installer_json_path,
         )
+        .await?;
+        read_json(&installer_json_path).await?
+    };
+
+    let version: CustomMeta = if version_json_path.is_file() {
+        read_json(&version_json_path).await?
+    } else {
+        download_installer(config, &installer_path, installer_url, emitter).await?;
+        extract_specific_file(&installer_path, "version.json", &version_json_path).await?;
+        read_json(&version_json_path).await?
+    };
+
+    process_data(config, loader_name, &installer_path, &mut installer.data).await?;
+
+    meta.data = Some(merge_data(
+        config,
+        &meta,
+        installer.data.unwrap_or_default(),
+    ));
+
+    meta.processors = installer.processors;
+
+    extract_specific_directory(&installer_path, "maven/", &config.get_libraries_path())
         .await
         .ok();
 
-        meta.libraries.retain(|lib| {
-            version
-                .libraries
-                .iter()
-                .all(|v_lib| v_lib.name.split(':').nth(1) != lib.name.split(':').nth(1))
-        });
-
-        let mut seen = HashSet::new();
-
-        meta.libraries
-            .extend(merge_libraries(config, version.libraries, &mut seen, false));
-        meta.libraries.extend(merge_libraries(
-            config,
-            installer.libraries,
-            &mut seen,
-            true,
-        ));
-
-        if let Some(ref mut arguments) = meta.arguments {
-            if let Some(jvm) = version.arguments.jvm {
-                arguments.jvm.extend(jvm);
-            }
-            if let Some(game) = version.arguments.game {
-                arguments.game.extend(game);
-            }
+    meta.libraries.retain(|lib| {
+        version
+            .libraries
+            .iter()
+            .all(|v_lib| v_lib.name.split(':').nth(1) != lib.name.split(':').nth(1))
+    });
+
+    let mut seen = HashSet::new();
+
+    meta.libraries
+        .extend(merge_libraries(config, version.libraries, &mut seen, false));
+    meta.libraries.extend(merge_libraries(
+        config,
+        installer.libraries,
+        &mut seen,
+        true,
+    ));
+
+    if let Some(ref mut arguments) = meta.arguments {
+        if let Some(jvm) = version.arguments.jvm {
+            arguments.jvm.extend(jvm);
+        }
+        if let Some(game) = version.arguments.game {
+            arguments.game.extend(game);
         }
-
-        meta.main_class = version.main_class;
-
-        Ok(meta)
     }
 
-    fn get_version(&self) -> String {
-        self.0.to_string()
-    }
+    meta.main_class = version.main_class;
+
+    Ok(meta)
 }
 
 async fn download_installer(
+    config: &Config,
     installer_path: &std::path::Path,
-    version_name: &str,
+    installer_url: &str,
     emitter: Option<&Emitter>,
 ) -> crate::Result<()> {
     if !installer_path.is_file() {
-        let installer_url = INSTALLER_JAR_ENDPOINT.replace("{loader_version}", version_name);
-        download(installer_url, installer_path, emitter).await?;
+        config
+            .mirrors
+            .download(
+                installer_url,
+                installer_path,
+                None,
+                &config.download_policy,
+                emitter,
+                config.cancellation_token.as_ref(),
+            )
+            .await?;
     }
     Ok(())
 }
 
 fn merge_data(
-    config: &Config<impl Loader>,
+    config: &Config,
     meta: &VersionMeta,
     installer_data: HashMap<String, Data>,
 ) -> HashMap<String, Data> {
@@ -190,18 +256,14 @@ fn merge_data(
         (
             "ROOT".to_string(),
             Data {
-                client: config.game_dir.to_string_lossy().into_owned(),
+                client: config.get_shared_path().to_string_lossy().into_owned(),
                 server: "".to_string(),
             },
         ),
         (
             "LIBRARY_DIR".to_string(),
             Data {
-                client: config
-                    .game_dir
-                    .join("libraries")
-                    .to_string_lossy()
-                    .into_owned(),
+                client: config.get_libraries_path().to_string_lossy().into_owned(),
                 server: "".to_string(),
             },
         ),
@@ -218,8 +280,13 @@ fn merge_data(
     .collect()
 }
 
+/// Extracts the files the processors read from the installer into the libraries.
+///
+/// The libraries are shared between instances, so the extracts are keyed by the
+/// loader and the version name to keep different builds of the same game version apart.
 async fn process_data(
-    config: &Config<impl Loader>,
+    config: &Config,
+    loader_name: &str,
     installer_path: &std::path::PathBuf,
     data: &mut Option<HashMap<String, Data>>,
 ) -> crate::Result<()> {
@@ -227,27 +294,30 @@ async fn process_data(
         for value in data.values_mut() {
             if value.client.starts_with('/') {
                 let file_path = &value.client[1..];
-                let file = file_path.split('/').last().ok_or(crate::Error::NotFound(
-                    "File not found for the processor".to_string(),
-                ))?;
+                let file = file_path
+                    .split('/')
+                    .next_back()
+                    .ok_or(crate::Error::NotFound(
+                        "File not found for the processor".to_string(),
+                    ))?;
                 let file_name = file.split('.').next().ok_or(crate::Error::NotFound(
                     "File name not found for the processor".to_string(),
                 ))?;
-                let ext = file.split('.').last().ok_or(crate::Error::NotFound(
+                let ext = file.split('.').next_back().ok_or(crate::Error::NotFound(
                     "File extension not found for the processor".to_string(),
                 ))?;
                 let path = format!(
-                    "com.cubidron.lyceris:forge-installer-extracts:{}:{}@{}",
-                    config.version, file_name, ext
+                    "com.cubidron.lyceris:{}-installer-extracts:{}:{}@{}",
+                    loader_name,
+                    config.get_version_name(),
+                    file_name,
+                    ext
                 );
 
                 extract_specific_file(
                     installer_path,
                     file_path,
-                    &config
-                        .game_dir
-                        .join("libraries")
-                        .join(parse_lib_path(&path)?),
+                    &config.get_libraries_path().join(parse_lib_path(&path)?),
                 )
                 .await?;
 
@@ -259,7 +329,7 @@ async fn process_data(
 }
 
 fn merge_libraries(
-    config: &Config<impl Loader>,
+    config: &Config,
     libraries: Vec<Library>,
     seen: &mut HashSet<String>,
     skip_args: bool,
@@ -286,6 +356,9 @@ fn merge_libraries(
                             sha1: lib.sha1.unwrap_or_default(),
                             size: lib.size.unwrap_or_default(),
                             url: format!("{}/{}", url, path),
+                            sha256: lib.sha256.clone(),
+                            sha512: lib.sha512.clone(),
+                            md5: lib.md5.clone(),
                         }),
                         classifiers: None,
                     }),
@@ -313,6 +386,9 @@ fn merge_libraries(
                                     sha1: lib.sha1.unwrap_or_default(),
                                     size: lib.size.unwrap_or_default(),
                                     url: artifact.url,
+                                    sha256: lib.sha256.clone(),
+                                    sha512: lib.sha512.clone(),
+                                    md5: lib.md5.clone(),
                                 }),
                                 classifiers: None,
                             }),
@@ -1,23 +1,225 @@
-#![allow(async_fn_in_trait)]
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use futures::future::BoxFuture;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
 use crate::json::version::meta::vanilla::VersionMeta;
 
-use super::{emitter::Emitter, config::Config};
+use self::{fabric::Fabric, forge::Forge, neoforge::NeoForge, quilt::Quilt};
+use super::{config::Config, emitter::Emitter};
 
 pub mod fabric;
-pub mod quilt;
 pub mod forge;
+pub mod neoforge;
+pub mod quilt;
 
-pub trait Loader { 
-    async fn merge<T: Loader>(&self, config: &Config<T>, meta: VersionMeta, emitter: Option<&Emitter>) -> crate::Result<VersionMeta>;
+/// Merges a mod loader into the vanilla version meta.
+///
+/// The trait is object safe so custom loaders can be plugged in
+/// through `LoaderKind::Custom`.
+pub trait Loader: Send + Sync {
+    fn merge<'a>(
+        &'a self,
+        config: &'a Config,
+        meta: VersionMeta,
+        emitter: Option<&'a Emitter>,
+    ) -> BoxFuture<'a, crate::Result<VersionMeta>>;
     fn get_version(&self) -> String;
 }
 
-impl Loader for () {
-    async fn merge<T: Loader>(&self, _: &Config<T>, meta: VersionMeta, _: Option<&Emitter>) -> crate::Result<VersionMeta> {
-        Ok(meta)
+/// The loader of a `Config`, selectable at runtime.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub enum LoaderKind {
+    #[default]
+    Vanilla,
+    Forge(Forge),
+    NeoForge(NeoForge),
+    Fabric(Fabric),
+    Quilt(Quilt),
+    /// A user provided loader. It is skipped by serialization, so configs
+    /// using it can't be persisted.
+    #[serde(skip)]
+    Custom(Arc<dyn Loader>),
+}
+
+impl LoaderKind {
+    pub fn custom<L: Loader + 'static>(loader: L) -> Self {
+        LoaderKind::Custom(Arc::new(loader))
+    }
+
+    pub fn is_vanilla(&self) -> bool {
+        matches!(self, LoaderKind::Vanilla)
+    }
+
+    fn as_loader(&self) -> Option<&dyn Loader> {
+        match self {
+            LoaderKind::Vanilla => None,
+            LoaderKind::Forge(loader) => Some(loader),
+            LoaderKind::NeoForge(loader) => Some(loader),
+            LoaderKind::Fabric(loader) => Some(loader),
+            LoaderKind::Quilt(loader) => Some(loader),
+            LoaderKind::Custom(loader) => Some(loader.as_ref()),
+        }
+    }
+
+    pub async fn merge(
+        &self,
+        config: &Config,
+        meta: VersionMeta,
+        emitter: Option<&Emitter>,
+    ) -> crate::Result<VersionMeta> {
+        match self.as_loader() {
+            Some(loader) => loader.merge(config, meta, emitter).await,
+            None => Ok(meta),
+        }
+    }
+
+    /// Returns the loader version, `None` for vanilla.
+    pub fn get_version(&self) -> Option<String> {
+        self.as_loader().map(|loader| loader.get_version())
+    }
+}
+
+impl fmt::Debug for LoaderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoaderKind::Vanilla => f.write_str("Vanilla"),
+            LoaderKind::Forge(loader) => f.debug_tuple("Forge").field(loader).finish(),
+            LoaderKind::NeoForge(loader) => f.debug_tuple("NeoForge").field(loader).finish(),
+            LoaderKind::Fabric(loader) => f.debug_tuple("Fabric").field(loader).finish(),
+            LoaderKind::Quilt(loader) => f.debug_tuple("Quilt").field(loader).finish(),
+            LoaderKind::Custom(loader) => f
+                .debug_tuple("Custom")
+                .field(&loader.get_version())
+                .finish(),
+        }
+    }
+}
+
+impl From<Forge> for LoaderKind {
+    fn from(loader: Forge) -> Self {
+        LoaderKind::Forge(loader)
+    }
+}
+
+impl From<NeoForge> for LoaderKind {
+    fn from(loader: NeoForge) -> Self {
+        LoaderKind::NeoForge(loader)
     }
+}
+
+impl From<Fabric> for LoaderKind {
+    fn from(loader: Fabric) -> Self {
+        LoaderKind::Fabric(loader)
+    }
+}
+
+impl From<Quilt> for LoaderKind {
+    fn from(loader: Quilt) -> Self {
+        LoaderKind::Quilt(loader)
+    }
+}
+
+/// A loader version available for a specific game version.
+///
+/// Lists returned by `list_versions` are ordered from newest to oldest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LoaderVersion {
+    pub version: String,
+    pub stable: bool,
+    pub recommended: bool,
+    pub latest: bool,
+}
+
+/// Picks the recommended version if there is one, otherwise the newest stable version.
+pub fn latest_stable(versions: &[LoaderVersion]) -> Option<&LoaderVersion> {
+    versions
+        .iter()
+        .find(|v| v.recommended)
+        .or_else(|| versions.iter().find(|v| v.stable))
+}
+
+/// Returns false for versions carrying a pre-release marker like `-beta` or `-rc.1`.
+pub(crate) fn is_stable(version: &str) -> bool {
+    let version = version.to_lowercase();
+    !["alpha", "beta", "pre", "rc"]
+        .iter()
+        .any(|marker| version.contains(marker))
+}
+
+/// Extracts every `<version>` entry of a Maven `maven-metadata.xml`
+/// and sorts them from newest to oldest.
+pub(crate) fn parse_maven_versions(metadata: &str) -> crate::Result<Vec<String>> {
+    let regex = Regex::new(r"<version>([^<]+)</version>")
+        .map_err(|e| crate::Error::Parse(e.to_string()))?;
+
+    let mut versions: Vec<String> = regex
+        .captures_iter(metadata)
+        .map(|capture| capture[1].trim().to_string())
+        .collect();
+
+    versions.sort_by(|a, b| compare_versions(b, a));
+
+    Ok(versions)
+}
+
+/// Compares dotted version strings component by component,
+/// numerically where both components are numbers.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split(['.', '-', '+']);
+    let mut b_parts = b.split(['.', '-', '+']);
+
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (Some(a), Some(b)) => {
+                let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    // A numeric component is newer than a pre-release tag.
+                    (Ok(_), Err(_)) => Ordering::Greater,
+                    (Err(_), Ok(_)) => Ordering::Less,
+                    (Err(_), Err(_)) => a.cmp(b),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            // `1.0` is newer than `1.0-beta`, but `1.0.1` is newer than `1.0`.
+            (Some(a), None) => {
+                return if a.parse::<u64>().is_ok() {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                }
+            }
+            (None, Some(b)) => {
+                return if b.parse::<u64>().is_ok() {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_maven_versions;
+
+    #[test]
+    fn parses_and_sorts_maven_metadata() {
+        let metadata = r#"<metadata><versioning><versions>
+            <version>21.1.9</version>
+            <version>21.1.10-beta</version>
+            <version>21.1.10</version>
+            <version>21.0.167</version>
+        </versions></versioning></metadata>"#;
 
-    fn get_version(&self) -> String {
-        "".to_string()
+        assert_eq!(
+            parse_maven_versions(metadata).unwrap(),
+            vec!["21.1.10", "21.1.10-beta", "21.1.9", "21.0.167"]
+        );
     }
 }
@@ -0,0 +1,126 @@
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    http::cache::MetadataSource,
+    json::version::meta::vanilla::VersionMeta,
+    minecraft::{config::Config, emitter::Emitter},
+};
+
+use super::{forge::merge_installer, is_stable, parse_maven_versions, Loader, LoaderVersion};
+
+const INSTALLER_JAR_ENDPOINT: &str = "https://maven.neoforged.net/releases/net/neoforged/neoforge/{loader_version}/neoforge-{loader_version}-installer.jar";
+
+/// NeoForge for Minecraft 1.20.1 was still published under the `forge` artifact.
+const LEGACY_INSTALLER_JAR_ENDPOINT: &str = "https://maven.neoforged.net/releases/net/neoforged/forge/{loader_version}/forge-{loader_version}-installer.jar";
+
+const LEGACY_GAME_VERSION: &str = "1.20.1";
+
+const MAVEN_METADATA_ENDPOINT: &str =
+    "https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml";
+
+const LEGACY_MAVEN_METADATA_ENDPOINT: &str =
+    "https://maven.neoforged.net/releases/net/neoforged/forge/maven-metadata.xml";
+
+/// NeoForge loader. The version is the NeoForge version without the
+/// Minecraft prefix (e.g. `21.1.77`), except for 1.20.1 where the legacy
+/// `47.1.x` numbering is used.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NeoForge(pub String);
+
+impl NeoForge {
+    /// Lists the NeoForge versions available for the given game version.
+    ///
+    /// NeoForge has no promotions, so the newest stable build is flagged as recommended.
+    pub async fn list_versions(
+        source: &MetadataSource,
+        game_version: &str,
+    ) -> crate::Result<Vec<LoaderVersion>> {
+        let versions = if game_version == LEGACY_GAME_VERSION {
+            let prefix = format!("{}-", LEGACY_GAME_VERSION);
+            parse_maven_versions(&source.fetch_text(LEGACY_MAVEN_METADATA_ENDPOINT).await?)?
+                .into_iter()
+                .filter_map(|version| version.strip_prefix(&prefix).map(str::to_string))
+                .collect::<Vec<_>>()
+        } else {
+            parse_maven_versions(&source.fetch_text(MAVEN_METADATA_ENDPOINT).await?)?
+                .into_iter()
+                .filter(|version| game_version_of(version).as_deref() == Some(game_version))
+                .collect::<Vec<_>>()
+        };
+
+        let recommended = versions.iter().position(|version| is_stable(version));
+
+        Ok(versions
+            .into_iter()
+            .enumerate()
+            .map(|(i, version)| LoaderVersion {
+                stable: is_stable(&version),
+                recommended: recommended == Some(i),
+                latest: i == 0,
+                version,
+            })
+            .collect())
+    }
+}
+
+impl Loader for NeoForge {
+    fn merge<'a>(
+        &'a self,
+        config: &'a Config,
+        meta: VersionMeta,
+        emitter: Option<&'a Emitter>,
+    ) -> BoxFuture<'a, crate::Result<VersionMeta>> {
+        Box::pin(async move {
+            let installer_url = if meta.id == LEGACY_GAME_VERSION {
+                let loader_version = if self.0.starts_with(LEGACY_GAME_VERSION) {
+                    self.0.clone()
+                } else {
+                    format!("{}-{}", LEGACY_GAME_VERSION, self.0)
+                };
+                LEGACY_INSTALLER_JAR_ENDPOINT.replace("{loader_version}", &loader_version)
+            } else {
+                if game_version_of(&self.0).as_deref() != Some(meta.id.as_str()) {
+                    return Err(Error::UnknownVersion("NeoForge".into()));
+                }
+                INSTALLER_JAR_ENDPOINT.replace("{loader_version}", &self.0)
+            };
+
+            merge_installer(config, meta, "neoforge", &installer_url, emitter).await
+        })
+    }
+
+    fn get_version(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// Returns the Minecraft version a NeoForge version targets.
+///
+/// NeoForge encodes the game version in its first two components,
+/// so `21.1.77` targets `1.21.1` and `21.0.167` targets `1.21`.
+pub(crate) fn game_version_of(loader_version: &str) -> Option<String> {
+    let mut parts = loader_version.split(['.', '-']);
+    let major: u32 = parts.next()?.parse().ok()?;
+    let minor: u32 = parts.next()?.parse().ok()?;
+
+    if minor == 0 {
+        Some(format!("1.{}", major))
+    } else {
+        Some(format!("1.{}.{}", major, minor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::game_version_of;
+
+    #[test]
+    fn maps_loader_version_to_game_version() {
+        assert_eq!(game_version_of("21.1.77").as_deref(), Some("1.21.1"));
+        assert_eq!(game_version_of("21.0.167").as_deref(), Some("1.21"));
+        assert_eq!(game_version_of("20.2.3-beta").as_deref(), Some("1.20.2"));
+        assert_eq!(game_version_of("invalid"), None);
+    }
+}
@@ -1,13 +1,14 @@
-use super::Loader;
+use super::{is_stable, Loader, LoaderVersion};
 use crate::{
     error::Error,
-    http::fetch::fetch,
+    http::cache::MetadataSource,
     json::version::meta::{
         custom::CustomMeta,
         vanilla::{self, VersionMeta},
     },
     minecraft::{config::Config, emitter::Emitter, parse::parse_lib_path},
 };
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
 const VERSION_META_ENDPOINT: &str = "https://meta.quiltmc.org/v3/";
@@ -28,86 +29,128 @@ enum Separator {
     Empty,
 }
 
+#[derive(Serialize, Deserialize)]
+struct GameLoader {
+    loader: QuiltLoader,
+}
+
 #[derive(Serialize, Deserialize)]
 struct Version {
     version: String,
     stable: bool,
 }
 
-pub struct Quilt(pub &'static str);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Quilt(pub String);
+
+impl Quilt {
+    /// Lists the Quilt loader versions available for the given game version.
+    pub async fn list_versions(
+        source: &MetadataSource,
+        game_version: &str,
+    ) -> crate::Result<Vec<LoaderVersion>> {
+        let loaders: Vec<GameLoader> = source
+            .fetch(&format!(
+                "{}versions/loader/{}",
+                VERSION_META_ENDPOINT, game_version
+            ))
+            .await?;
+
+        // The meta API has no notion of recommended builds and
+        // already orders the loaders from newest to oldest.
+        Ok(loaders
+            .into_iter()
+            .enumerate()
+            .map(|(i, loader)| LoaderVersion {
+                stable: is_stable(&loader.loader.version),
+                version: loader.loader.version,
+                recommended: false,
+                latest: i == 0,
+            })
+            .collect())
+    }
+}
 
 impl Loader for Quilt {
-    async fn merge<T: Loader>(
-        &self,
-        _config: &Config<T>,
+    fn merge<'a>(
+        &'a self,
+        config: &'a Config,
         mut meta: VersionMeta,
-        _emitter: Option<&Emitter>,
-    ) -> crate::Result<VersionMeta> {
-        let loaders: Vec<QuiltLoader> =
-            fetch(format!("{}versions/loader", VERSION_META_ENDPOINT)).await?;
-        let versions: Vec<Version> =
-            fetch(format!("{}versions/game", VERSION_META_ENDPOINT)).await?;
-
-        let loader = loaders
-            .into_iter()
-            .find(|v| v.version == self.0)
-            .ok_or_else(|| Error::UnknownVersion("Quilt Loader".into()))?;
-        let fabric = versions
-            .into_iter()
-            .find(|v| v.version == meta.id)
-            .ok_or_else(|| Error::UnknownVersion("Quilt".into()))?;
-
-        let version: CustomMeta = fetch(format!(
-            "{}versions/loader/{}/{}/profile/json",
-            VERSION_META_ENDPOINT, fabric.version, loader.version
-        ))
-        .await?;
-
-        meta.libraries.retain(|lib| {
-            version
-                .libraries
-                .iter()
-                .all(|v_lib| v_lib.name.split(':').nth(1) != lib.name.split(':').nth(1))
-        });
-
-        meta.libraries.extend(
-            version
-                .libraries
+        _emitter: Option<&'a Emitter>,
+    ) -> BoxFuture<'a, crate::Result<VersionMeta>> {
+        Box::pin(async move {
+            let loaders: Vec<QuiltLoader> = config
+                .fetch_metadata(&format!("{}versions/loader", VERSION_META_ENDPOINT))
+                .await?;
+            let versions: Vec<Version> = config
+                .fetch_metadata(&format!("{}versions/game", VERSION_META_ENDPOINT))
+                .await?;
+
+            let loader = loaders
+                .into_iter()
+                .find(|v| v.version == self.0)
+                .ok_or_else(|| Error::UnknownVersion("Quilt Loader".into()))?;
+            let fabric = versions
                 .into_iter()
-                .filter_map(|lib| {
-                    let path = parse_lib_path(&lib.name).ok()?;
-                    lib.url.map(|url| vanilla::Library {
-                        downloads: Some(vanilla::LibraryDownloads {
-                            artifact: Some(vanilla::File {
-                                path: Some(path.clone()),
-                                sha1: lib.sha1.unwrap_or_default(),
-                                size: lib.size.unwrap_or_default(),
-                                url: format!("{}/{}", url, path),
+                .find(|v| v.version == meta.id)
+                .ok_or_else(|| Error::UnknownVersion("Quilt".into()))?;
+
+            let version: CustomMeta = config
+                .fetch_metadata(&format!(
+                    "{}versions/loader/{}/{}/profile/json",
+                    VERSION_META_ENDPOINT, fabric.version, loader.version
+                ))
+                .await?;
+
+            meta.libraries.retain(|lib| {
+                version
+                    .libraries
+                    .iter()
+                    .all(|v_lib| v_lib.name.split(':').nth(1) != lib.name.split(':').nth(1))
+            });
+
+            meta.libraries.extend(
+                version
+                    .libraries
+                    .into_iter()
+                    .filter_map(|lib| {
+                        let path = parse_lib_path(&lib.name).ok()?;
+                        lib.url.map(|url| vanilla::Library {
+                            downloads: Some(vanilla::LibraryDownloads {
+                                artifact: Some(vanilla::File {
+                                    path: Some(path.clone()),
+                                    sha1: lib.sha1.unwrap_or_default(),
+                                    size: lib.size.unwrap_or_default(),
+                                    url: format!("{}/{}", url, path),
+                                    sha256: lib.sha256.clone(),
+                                    sha512: lib.sha512.clone(),
+                                    md5: lib.md5.clone(),
+                                }),
+                                classifiers: None,
                             }),
-                            classifiers: None,
-                        }),
-                        extract: None,
-                        name: lib.name.clone(),
-                        rules: None,
-                        natives: None,
-                        skip_args: false,
+                            extract: None,
+                            name: lib.name.clone(),
+                            rules: None,
+                            natives: None,
+                            skip_args: false,
+                        })
                     })
-                })
-                .collect::<Vec<_>>(),
-        );
+                    .collect::<Vec<_>>(),
+            );
 
-        if let Some(ref mut arguments) = meta.arguments {
-            if let Some(jvm) = version.arguments.jvm {
-                arguments.jvm.extend(jvm);
-            }
-            if let Some(game) = version.arguments.game {
-                arguments.game.extend(game);
+            if let Some(ref mut arguments) = meta.arguments {
+                if let Some(jvm) = version.arguments.jvm {
+                    arguments.jvm.extend(jvm);
+                }
+                if let Some(game) = version.arguments.game {
+                    arguments.game.extend(game);
+                }
             }
-        }
 
-        meta.main_class = version.main_class;
+            meta.main_class = version.main_class;
 
-        Ok(meta)
+            Ok(meta)
+        })
     }
 
     fn get_version(&self) -> String {
@@ -1,6 +1,9 @@
 use std::env::consts::OS;
 
-use crate::{error::Error, json::version::meta::vanilla::{Action, Name, Rule}};
+use crate::{
+    error::Error,
+    json::version::meta::vanilla::{Action, Name, Rule},
+};
 
 use super::TARGET_ARCH;
 
@@ -115,7 +118,10 @@ impl ParseRule for Option<Vec<Rule>> {
 pub fn parse_lib_path(artifact: &str) -> crate::Result<String> {
     let name_items: Vec<&str> = artifact.split(':').collect();
     if name_items.len() < 3 {
-        return Err(Error::Parse(format!("Invalid artifact format: {}", artifact)));
+        return Err(Error::Parse(format!(
+            "Invalid artifact format: {}",
+            artifact
+        )));
     }
 
     let package = name_items[0];
@@ -150,4 +156,4 @@ pub fn parse_lib_path(artifact: &str) -> crate::Result<String> {
             data_ext
         ))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf, MAIN_SEPARATOR_STR},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    http::downloader::part_path,
+    json::java::JavaFileManifest,
+    util::{
+        hash::{calculate_hash, Checksum},
+        verification::VerificationCache,
+    },
+};
+
+use super::{
+    config::Config,
+    emitter::{Emit, Emitter, Event, Phase},
+    install::{build_plan, execute_plan, install, load_metadata, DownloadFile, InstallPlan},
+};
+
+/// A file whose size or hash doesn't match the metadata.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CorruptFile {
+    pub path: PathBuf,
+    pub expected_checksum: Option<Checksum>,
+    /// Hash of the file with the algorithm of `expected_checksum`,
+    /// not computed when the size already differs.
+    pub actual_hash: Option<String>,
+    pub expected_size: u64,
+    pub actual_size: u64,
+}
+
+/// The state of an installation compared to its metadata.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VerifyReport {
+    /// Files that don't exist, including the cached metadata itself.
+    pub missing: Vec<PathBuf>,
+    pub corrupt: Vec<CorruptFile>,
+    /// Files that don't belong to the installation, which are files in the
+    /// Java runtime that aren't in its manifest and leftover `.part` downloads.
+    pub extra: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    /// Returns whether nothing is missing or corrupt, extra files are ignored.
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+}
+
+/// Checks every file of an installation against the cached metadata without touching the network.
+///
+/// The version JSON, asset index, libraries, natives and Java runtime are
+/// checked by size and hash. Every file is hashed, the verification cache is
+/// only refreshed and never trusted. When the metadata itself isn't cached,
+/// the report only lists the missing files without checking any hash.
+pub async fn verify(config: &Config, emitter: Option<&Emitter>) -> crate::Result<VerifyReport> {
+    Ok(inspect(config, emitter).await?.0)
+}
+
+/// Verifies an installation and downloads the missing and corrupt files again.
+///
+/// Missing metadata is fetched again through a regular `install`. Extra files
+/// are left alone. Returns the report of the installation before the repair.
+pub async fn repair(config: &Config, emitter: Option<&Emitter>) -> crate::Result<VerifyReport> {
+    let (report, plan) = inspect(config, emitter).await?;
+
+    match plan {
+        None => install(config, emitter).await?,
+        Some(plan) if !report.is_healthy() => execute_plan(&plan, config, emitter).await?,
+        Some(_) => {}
+    }
+
+    Ok(report)
+}
+
+/// Verifies the installation and returns a plan downloading the broken files,
+/// or no plan when the metadata isn't cached.
+async fn inspect(
+    config: &Config,
+    emitter: Option<&Emitter>,
+) -> crate::Result<(VerifyReport, Option<InstallPlan>)> {
+    let metadata = match load_metadata(config, true, emitter).await {
+        Ok(metadata) => metadata,
+        Err(Error::MissingFiles(missing)) => {
+            return Ok((
+                VerifyReport {
+                    missing,
+                    ..Default::default()
+                },
+                None,
+            ))
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut plan = build_plan(config, &metadata, true)?;
+
+    emitter.emit(Event::PhaseStarted(Phase::Verification));
+    let cache_path = config.get_verification_cache_path();
+    // Files changed without touching their size or modification time are
+    // exactly what verify has to find, so the cache is bypassed.
+    let cache = Arc::new(VerificationCache::load(&cache_path, true).await);
+    let mut files = std::mem::take(&mut plan.files);
+    let blocking_emitter = emitter.cloned();
+    let blocking_cache = cache.clone();
+    let (files, mut report) = tokio::task::spawn_blocking(move || {
+        let report = Mutex::new(VerifyReport::default());
+        let checked = AtomicUsize::new(0);
+        let total = files.len();
+        files.par_iter_mut().for_each(|file| {
+            check_file(file, &blocking_cache, &report);
+            blocking_emitter.as_ref().emit(Event::FileVerified {
+                path: file.path.clone(),
+                checked: checked.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+            });
+        });
+        let report = report
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        (files, report)
+    })
+    .await?;
+    plan.files = files;
+    cache.save(&cache_path).await?;
+    emitter.emit(Event::PhaseFinished(Phase::Verification));
+
+    report.extra = plan
+        .files
+        .iter()
+        .map(|file| part_path(&file.path))
+        .filter(|path| path.is_file())
+        .collect();
+    report.extra.extend(extra_runtime_files(
+        &plan.java_runtime.path,
+        &metadata.java_files,
+    )?);
+
+    report.missing.sort();
+    report.corrupt.sort_by(|a, b| a.path.cmp(&b.path));
+    report.extra.sort();
+
+    Ok((report, Some(plan)))
+}
+
+/// Adds the file to the report and marks it for download when it's missing or corrupt.
+fn check_file(file: &mut DownloadFile, cache: &VerificationCache, report: &Mutex<VerifyReport>) {
+    // Files without a URL are generated locally, e.g. by loader processors.
+    if file.url.is_empty() {
+        return;
+    }
+
+    let Ok(metadata) = fs::metadata(&file.path) else {
+        file.download = true;
+        lock(report).missing.push(file.path.clone());
+        return;
+    };
+
+    let actual_size = metadata.len();
+    let actual_hash = if file.size > 0 && actual_size != file.size {
+        None
+    } else if let Some(checksum) = &file.checksum {
+        if cache.is_verified(&file.path, checksum) {
+            return;
+        }
+        match calculate_hash(&file.path, checksum.algorithm) {
+            Ok(hash) if hash.eq_ignore_ascii_case(&checksum.value) => {
+                cache.insert(&file.path, checksum);
+                return;
+            }
+            Ok(hash) => Some(hash),
+            Err(_) => None,
+        }
+    } else {
+        return;
+    };
+
+    file.download = true;
+    lock(report).corrupt.push(CorruptFile {
+        path: file.path.clone(),
+        expected_checksum: file.checksum.clone(),
+        actual_hash,
+        expected_size: file.size,
+        actual_size,
+    });
+}
+
+/// Returns the files in the runtime directory that aren't listed in its manifest.
+fn extra_runtime_files(
+    runtime_path: &Path,
+    java_files: &JavaFileManifest,
+) -> crate::Result<Vec<PathBuf>> {
+    let known: HashSet<PathBuf> = java_files
+        .files
+        .keys()
+        .map(|name| runtime_path.join(name.replace('/', MAIN_SEPARATOR_STR)))
+        .collect();
+
+    let mut extra = Vec::new();
+    let mut stack = vec![runtime_path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() && !path.is_symlink() {
+                stack.push(path);
+            } else if !known.contains(&path) && path.extension().is_none_or(|ext| ext != "part") {
+                extra.push(path);
+            }
+        }
+    }
+
+    Ok(extra)
+}
+
+fn lock(report: &Mutex<VerifyReport>) -> std::sync::MutexGuard<'_, VerifyReport> {
+    report
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{repair, verify};
+    use crate::{
+        auth::AuthMethod,
+        http::downloader::{part_path, tests::serve},
+        minecraft::{
+            config::{Config, ConfigBuilder},
+            install::tests::{game_files, test_config, version_meta, write_metadata},
+        },
+    };
+
+    /// Installs every file of the fixture, then breaks the client jar by size and
+    /// the library by hash, and leaves a download and a stray runtime file behind.
+    async fn broken_install() -> Config {
+        let url = serve(false).await;
+        let config = test_config(&url).build();
+        write_metadata(&config, &version_meta(&url), &url);
+
+        let [client, asset, library, java] = game_files(&config);
+        for path in [&client, &asset, &library, &java] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"lyceris").unwrap();
+        }
+        std::fs::write(&client, b"lyc").unwrap();
+        std::fs::write(&library, b"LYCERIS").unwrap();
+        std::fs::write(part_path(&asset), b"lyc").unwrap();
+        std::fs::write(java.with_file_name("stray"), b"").unwrap();
+
+        config
+    }
+
+    #[tokio::test]
+    async fn reports_corrupt_and_extra_files() {
+        let config = broken_install().await;
+        let [client, asset, library, java] = game_files(&config);
+
+        let report = verify(&config, None).await.unwrap();
+
+        assert!(!report.is_healthy());
+        assert!(report.missing.is_empty());
+        let corrupt: Vec<_> = report.corrupt.iter().map(|file| &file.path).collect();
+        let mut expected = vec![&client, &library];
+        expected.sort();
+        assert_eq!(corrupt, expected);
+
+        let client_report = report.corrupt.iter().find(|f| f.path == client).unwrap();
+        assert_eq!(
+            (client_report.expected_size, client_report.actual_size),
+            (7, 3)
+        );
+        assert!(client_report.actual_hash.is_none());
+        let library_report = report.corrupt.iter().find(|f| f.path == library).unwrap();
+        assert!(library_report.actual_hash.is_some());
+
+        let mut extra = vec![part_path(&asset), java.with_file_name("stray")];
+        extra.sort();
+        assert_eq!(report.extra, extra);
+
+        std::fs::remove_dir_all(&config.game_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn repairs_only_the_broken_files() {
+        let config = broken_install().await;
+        let [client, asset, library, java] = game_files(&config);
+        let modified =
+            |path: &std::path::Path| std::fs::metadata(path).unwrap().modified().unwrap();
+        let untouched = [modified(&asset), modified(&java)];
+
+        let report = repair(&config, None).await.unwrap();
+        assert_eq!(report.corrupt.len(), 2);
+
+        assert_eq!(std::fs::read(&client).unwrap(), b"lyceris");
+        assert_eq!(std::fs::read(&library).unwrap(), b"lyceris");
+        assert_eq!([modified(&asset), modified(&java)], untouched);
+        assert!(verify(&config, None).await.unwrap().is_healthy());
+
+        std::fs::remove_dir_all(&config.game_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_missing_metadata_without_fetching() {
+        let game_dir =
+            std::env::temp_dir().join(format!("lyceris-verify-{}", uuid::Uuid::new_v4()));
+        let config = ConfigBuilder::new(
+            &game_dir,
+            "1.21.4",
+            AuthMethod::Offline {
+                username: "Lyceris".into(),
+                uuid: None,
+            },
+        )
+        .build();
+
+        let report = verify(&config, None).await.unwrap();
+
+        assert!(!report.is_healthy());
+        assert_eq!(report.missing, vec![config.get_version_json_path()]);
+    }
+}
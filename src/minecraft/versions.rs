@@ -0,0 +1,180 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::{
+    error::Error,
+    http::cache::MetadataSource,
+    json::version::manifest::{Type, Version, VersionManifest},
+};
+
+use super::VERSION_MANIFEST_ENDPOINT;
+
+/// Alias resolving to the latest release in the manifest.
+pub const LATEST_RELEASE: &str = "latest-release";
+/// Alias resolving to the latest snapshot in the manifest.
+pub const LATEST_SNAPSHOT: &str = "latest-snapshot";
+
+/// Filter applied to the versions of a `VersionManifest`.
+///
+/// Release time bounds are either full RFC 3339 timestamps
+/// (`2021-01-15T14:14:48+00:00`) or plain dates (`2021-01-15`) in UTC,
+/// and are compared as instants against the release times of the manifest.
+/// Versions with a release time that can't be parsed never match a bound.
+#[derive(Default, Clone, Debug)]
+pub struct VersionFilter {
+    types: Vec<Type>,
+    released_after: Option<DateTime<FixedOffset>>,
+    released_before: Option<DateTime<FixedOffset>>,
+}
+
+impl VersionFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keeps versions of the given type. Can be called multiple times
+    /// to accept several types, no type filter means every type is kept.
+    pub fn r#type(mut self, r#type: Type) -> Self {
+        self.types.push(r#type);
+        self
+    }
+
+    /// Only keeps versions released at or after the given time,
+    /// a plain date starts at midnight UTC.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Parse` when the time is neither an RFC 3339 timestamp nor a date.
+    pub fn released_after(mut self, time: &str) -> crate::Result<Self> {
+        self.released_after = Some(parse_bound(time, false)?);
+        Ok(self)
+    }
+
+    /// Only keeps versions released at or before the given time,
+    /// a plain date includes the whole day in UTC.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Parse` when the time is neither an RFC 3339 timestamp nor a date.
+    pub fn released_before(mut self, time: &str) -> crate::Result<Self> {
+        self.released_before = Some(parse_bound(time, true)?);
+        Ok(self)
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&version.r#type) {
+            return false;
+        }
+        if self.released_after.is_none() && self.released_before.is_none() {
+            return true;
+        }
+        let Ok(release_time) = DateTime::parse_from_rfc3339(&version.release_time) else {
+            return false;
+        };
+        self.released_after
+            .is_none_or(|after| release_time >= after)
+            && self
+                .released_before
+                .is_none_or(|before| release_time <= before)
+    }
+}
+
+/// Parses a time bound, a plain date is taken as the start or the end of the day in UTC.
+fn parse_bound(time: &str, end_of_day: bool) -> crate::Result<DateTime<FixedOffset>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+        return Ok(time);
+    }
+
+    let date = NaiveDate::parse_from_str(time, "%Y-%m-%d")
+        .map_err(|_| Error::Parse(format!("Release time '{}'", time)))?;
+    let time = if end_of_day {
+        date.and_hms_nano_opt(23, 59, 59, 999_999_999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }
+    .ok_or_else(|| Error::Parse(format!("Release time '{}'", time)))?;
+    Ok(time.and_utc().fixed_offset())
+}
+
+impl VersionManifest {
+    /// Returns the versions matching the filter, newest first as listed by the manifest.
+    pub fn filter(&self, filter: &VersionFilter) -> Vec<&Version> {
+        self.versions.iter().filter(|v| filter.matches(v)).collect()
+    }
+
+    /// Finds a version by its id, also resolving the `latest-release`
+    /// and `latest-snapshot` aliases.
+    pub fn resolve(&self, version: &str) -> Option<&Version> {
+        let id = match version {
+            LATEST_RELEASE => &self.latest.release,
+            LATEST_SNAPSHOT => &self.latest.snapshot,
+            id => id,
+        };
+        self.versions.iter().find(|v| v.id == id)
+    }
+}
+
+/// Fetches the vanilla version manifest through the mirrors and metadata cache of the source.
+pub async fn fetch_manifest(source: &MetadataSource) -> crate::Result<VersionManifest> {
+    source.fetch(VERSION_MANIFEST_ENDPOINT).await
+}
+
+/// Fetches the vanilla version manifest and returns the versions matching the filter.
+pub async fn list_versions(
+    source: &MetadataSource,
+    filter: &VersionFilter,
+) -> crate::Result<Vec<Version>> {
+    let manifest = fetch_manifest(source).await?;
+    Ok(manifest.filter(filter).into_iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VersionFilter, LATEST_RELEASE};
+    use crate::json::version::manifest::{Latest, Type, Version, VersionManifest};
+
+    fn version(id: &str, r#type: Type, release_time: &str) -> Version {
+        Version {
+            id: id.to_string(),
+            r#type,
+            url: String::new(),
+            time: release_time.to_string(),
+            release_time: release_time.to_string(),
+        }
+    }
+
+    #[test]
+    fn filters_and_resolves_versions() {
+        let manifest = VersionManifest {
+            latest: Latest {
+                release: "1.21.4".to_string(),
+                snapshot: "25w02a".to_string(),
+            },
+            versions: vec![
+                version("25w02a", Type::Snapshot, "2025-01-08T13:12:42+00:00"),
+                version("1.21.4", Type::Release, "2024-12-03T10:12:57+00:00"),
+                version("1.20.1", Type::Release, "2023-06-12T13:25:51+00:00"),
+            ],
+        };
+
+        let filter = VersionFilter::new()
+            .r#type(Type::Release)
+            .released_before("2024-12-03")
+            .unwrap();
+        let ids: Vec<_> = manifest.filter(&filter).iter().map(|v| &v.id).collect();
+        assert_eq!(ids, vec!["1.21.4", "1.20.1"]);
+
+        // Offsets are compared as instants, 10:12:57+00:00 is 12:12:57+02:00.
+        let filter = VersionFilter::new()
+            .released_after("2024-12-03T12:12:57+02:00")
+            .unwrap()
+            .released_before("2024-12-03T11:15:00+01:00")
+            .unwrap();
+        let ids: Vec<_> = manifest.filter(&filter).iter().map(|v| &v.id).collect();
+        assert_eq!(ids, vec!["1.21.4"]);
+
+        assert!(VersionFilter::new().released_after("last week").is_err());
+
+        assert_eq!(manifest.resolve(LATEST_RELEASE).unwrap().id, "1.21.4");
+        assert!(manifest.resolve("1.0").is_none());
+    }
+}
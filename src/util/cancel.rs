@@ -0,0 +1,30 @@
+use std::future::Future;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::error::Error;
+
+/// Runs the future until it completes or the token is cancelled.
+///
+/// The future is dropped as soon as the token is cancelled and
+/// `Error::Cancelled` is returned instead of its output.
+pub async fn cancellable<F: Future>(
+    cancel: Option<&CancellationToken>,
+    future: F,
+) -> crate::Result<F::Output> {
+    match cancel {
+        Some(token) => tokio::select! {
+            _ = token.cancelled() => Err(Error::Cancelled),
+            output = future => Ok(output),
+        },
+        None => Ok(future.await),
+    }
+}
+
+/// Returns `Error::Cancelled` if the token has already been cancelled.
+pub fn check_cancelled(cancel: Option<&CancellationToken>) -> crate::Result<()> {
+    match cancel {
+        Some(token) if token.is_cancelled() => Err(Error::Cancelled),
+        _ => Ok(()),
+    }
+}
@@ -1,11 +1,177 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
 use sha1::{Digest, Sha1};
-use std::{fs::File, io::Read, path::Path};
+use sha2::{Sha256, Sha512};
+use std::{
+    fmt::Write,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-pub fn calculate_sha1<P: AsRef<Path>>(path: P) -> crate::Result<String> {
+/// Size of the buffer files are streamed through while hashing.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+    Md5,
+}
+
+/// An expected hash of a file as a lowercase hex string.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: HashAlgorithm,
+    pub value: String,
+}
+
+impl Checksum {
+    pub fn new(algorithm: HashAlgorithm, value: impl Into<String>) -> Self {
+        Self {
+            algorithm,
+            value: value.into(),
+        }
+    }
+
+    /// Returns a SHA1 checksum, or `None` when the hash is empty.
+    pub fn sha1(value: impl Into<String>) -> Option<Self> {
+        let value = value.into();
+        (!value.is_empty()).then(|| Self::new(HashAlgorithm::Sha1, value))
+    }
+
+    /// Returns the strongest of the given hashes, ignoring empty ones.
+    pub fn strongest(
+        sha1: Option<&str>,
+        sha256: Option<&str>,
+        sha512: Option<&str>,
+        md5: Option<&str>,
+    ) -> Option<Self> {
+        [
+            (HashAlgorithm::Sha512, sha512),
+            (HashAlgorithm::Sha256, sha256),
+            (HashAlgorithm::Sha1, sha1),
+            (HashAlgorithm::Md5, md5),
+        ]
+        .into_iter()
+        .find_map(|(algorithm, value)| {
+            value
+                .filter(|value| !value.is_empty())
+                .map(|value| Self::new(algorithm, value.to_lowercase()))
+        })
+    }
+
+    /// Returns whether the file at the given path matches this checksum.
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> crate::Result<bool> {
+        Ok(calculate_hash(path, self.algorithm)?.eq_ignore_ascii_case(&self.value))
+    }
+}
+
+/// Incremental hasher for any of the supported algorithms.
+#[derive(Clone)]
+pub enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Md5(Md5),
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+            Hasher::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the hash as a lowercase hex string.
+    pub fn finalize(self) -> String {
+        let bytes = match self {
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+        };
+        bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+    }
+}
+
+/// Hashes a file with the given algorithm, streaming it through a fixed size buffer.
+pub fn calculate_hash<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm) -> crate::Result<String> {
     let mut file = File::open(path)?;
-    let mut hasher = Sha1::new();
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    hasher.update(&buffer);
-    Ok(format!("{:x}", hasher.finalize()))
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
+
+pub fn calculate_sha1<P: AsRef<Path>>(path: P) -> crate::Result<String> {
+    calculate_hash(path, HashAlgorithm::Sha1)
+}
+
+/// Hashes a file on the blocking thread pool so the async runtime isn't blocked.
+pub async fn calculate_hash_async(
+    path: PathBuf,
+    algorithm: HashAlgorithm,
+) -> crate::Result<String> {
+    tokio::task::spawn_blocking(move || calculate_hash(path, algorithm)).await?
+}
+
+pub async fn calculate_sha1_async(path: PathBuf) -> crate::Result<String> {
+    calculate_hash_async(path, HashAlgorithm::Sha1).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calculate_hash, Checksum, HashAlgorithm};
+
+    #[test]
+    fn hashes_files_with_every_algorithm() {
+        let path = std::env::temp_dir().join(format!("lyceris-hash-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"lyceris").unwrap();
+
+        for (algorithm, expected) in [
+            (
+                HashAlgorithm::Sha1,
+                "218f21ba45c6279acaf24b8e661e9ee80586b084",
+            ),
+            (
+                HashAlgorithm::Sha256,
+                "1dd9a363bd5e267365a948efd709396324aaa1a51e21cc10a86169916e3b00ec",
+            ),
+            (
+                HashAlgorithm::Sha512,
+                "99a8392b317e2777e4abe740da452376f76358dbecb352f082a072958658f74d2627c4856f638be876f2b3800e975416c161c569e75a9c67e839ec4fce9634e6",
+            ),
+            (HashAlgorithm::Md5, "a4b94064b4e1376249a8281953960a09"),
+        ] {
+            assert_eq!(calculate_hash(&path, algorithm).unwrap(), expected);
+        }
+
+        let checksum = Checksum::strongest(Some(""), None, None, Some("ABC")).unwrap();
+        assert_eq!(checksum, Checksum::new(HashAlgorithm::Md5, "abc"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
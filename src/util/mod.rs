@@ -1,5 +1,7 @@
+pub mod base64;
+pub mod cancel;
 pub mod extract;
 pub mod hash;
 pub mod json;
 pub mod retry;
-pub mod base64;
\ No newline at end of file
+pub mod verification;
@@ -4,13 +4,14 @@ use std::time::Duration;
 /// Retries a given asynchronous operation a specified number of times with a delay.
 ///
 /// This function attempts to execute the provided operation up to `max_retries` times.
-/// If the operation fails, it waits for the specified `delay` before retrying. If the
-/// operation succeeds, the result is returned. If all attempts fail, an error is returned.
+/// If the operation fails, it waits for the duration returned by `delay` before retrying.
+/// If the operation succeeds, the result is returned. If all attempts fail, an error is returned.
 ///
 /// # Parameters
 ///
 /// - `max_retries`: The maximum number of times to retry the operation.
-/// - `delay`: The duration to wait between retry attempts.
+/// - `delay`: A closure returning the duration to wait after the given failed attempt,
+///   starting from 1. Use `|_| duration` for a fixed delay.
 /// - `operation`: A closure that returns a `Result<T, String>`. This closure is the
 ///   operation to be retried. It should return `Ok(T)` on success or `Err(String)`
 ///   on failure.
@@ -30,7 +31,7 @@ pub async fn retry<A, B: std::future::Future<Output = A>>(
     f: impl Fn() -> B,
     handler: impl Fn(&A) -> bool,
     max_retries: u32,
-    delay: Duration,
+    delay: impl Fn(u32) -> Duration,
 ) -> A {
     let mut retries = 0;
     loop {
@@ -40,6 +41,6 @@ pub async fn retry<A, B: std::future::Future<Output = A>>(
         if handler(&r) || retries >= max_retries {
             return r;
         }
-        tokio::time::sleep(delay).await;
+        tokio::time::sleep(delay(retries)).await;
     }
 }
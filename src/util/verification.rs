@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    hash::Checksum,
+    json::{read_json, write_json},
+};
+
+/// A file that matched its checksum along with the state it was in at the time.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Entry {
+    size: u64,
+    modified: SystemTime,
+    checksum: Checksum,
+}
+
+/// Remembers the files that were already hashed so unchanged files aren't hashed again.
+///
+/// An entry is only trusted while the size and modification time of the file
+/// are the same as when it was verified and the expected checksum didn't change.
+#[derive(Default)]
+pub struct VerificationCache {
+    entries: RwLock<HashMap<PathBuf, Entry>>,
+    /// Ignores the entries, every file is hashed and recorded again.
+    force: bool,
+    changed: AtomicBool,
+}
+
+impl VerificationCache {
+    /// Reads the cache at the given path, a missing or corrupt cache is treated as empty.
+    pub async fn load<P: AsRef<Path>>(path: P, force: bool) -> Self {
+        let entries = read_json(path).await.unwrap_or_default();
+        Self {
+            entries: RwLock::new(entries),
+            force,
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    /// Writes the cache to the given path if anything was recorded since it was loaded.
+    pub async fn save<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let entries = self
+            .entries
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        write_json(path, &entries).await
+    }
+
+    /// Returns whether the file was verified against the checksum and didn't change since.
+    pub fn is_verified(&self, path: &Path, checksum: &Checksum) -> bool {
+        if self.force {
+            return false;
+        }
+        let entries = self
+            .entries
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(entry) = entries.get(path) else {
+            return false;
+        };
+        let Ok((size, modified)) = stat(path) else {
+            return false;
+        };
+        entry.size == size && entry.modified == modified && &entry.checksum == checksum
+    }
+
+    /// Records that the file currently matches the checksum.
+    pub fn insert(&self, path: &Path, checksum: &Checksum) {
+        let Ok((size, modified)) = stat(path) else {
+            return;
+        };
+        self.entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(
+                path.to_path_buf(),
+                Entry {
+                    size,
+                    modified,
+                    checksum: checksum.clone(),
+                },
+            );
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the file matches the checksum, hashing it only when it isn't cached.
+    pub fn matches(&self, path: &Path, checksum: &Checksum) -> crate::Result<bool> {
+        if self.is_verified(path, checksum) {
+            return Ok(true);
+        }
+        let matches = checksum.matches(path)?;
+        if matches {
+            self.insert(path, checksum);
+        }
+        Ok(matches)
+    }
+}
+
+fn stat(path: &Path) -> std::io::Result<(u64, SystemTime)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.len(), metadata.modified()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VerificationCache;
+    use crate::util::hash::{calculate_sha1, Checksum, HashAlgorithm};
+
+    #[tokio::test]
+    async fn skips_unchanged_files_until_forced() {
+        let dir =
+            std::env::temp_dir().join(format!("lyceris-verification-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file");
+        let cache_path = dir.join("verification.json");
+        std::fs::write(&file, b"lyceris").unwrap();
+        let checksum = Checksum::new(HashAlgorithm::Sha1, calculate_sha1(&file).unwrap());
+
+        let cache = VerificationCache::load(&cache_path, false).await;
+        assert!(!cache.is_verified(&file, &checksum));
+        assert!(cache.matches(&file, &checksum).unwrap());
+        cache.save(&cache_path).await.unwrap();
+
+        let cache = VerificationCache::load(&cache_path, false).await;
+        assert!(cache.is_verified(&file, &checksum));
+        let other = Checksum::new(HashAlgorithm::Sha1, "0".repeat(40));
+        assert!(!cache.is_verified(&file, &other));
+
+        let forced = VerificationCache::load(&cache_path, true).await;
+        assert!(!forced.is_verified(&file, &checksum));
+
+        // A different size invalidates the entry even with the same modification time.
+        std::fs::write(&file, b"lyceris!").unwrap();
+        assert!(!cache.is_verified(&file, &checksum));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}